@@ -0,0 +1,94 @@
+use crate::Config;
+use crate::Result;
+use crate::Terminal;
+use std::env;
+use std::mem;
+use std::process::{Command, Stdio};
+
+/// paste helpers tried in order when no command is configured
+const PASTE_COMMANDS: [(&str, &str); 4] = [
+    ("WAYLAND_DISPLAY", "wl-paste --no-newline"),
+    ("DISPLAY", "xclip -selection clipboard -o"),
+    ("DISPLAY", "xsel --clipboard --output"),
+    ("", "pbpaste"),
+];
+
+/// copied text, kept locally and mirrored to the system clipboard when possible
+#[derive(Default)]
+pub struct Clipboard {
+    text: String,
+    /// whether `text` was copied from a rectangular selection
+    block: bool,
+    osc52: bool,
+    paste_command: Option<String>,
+}
+
+impl Clipboard {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            text: String::new(),
+            block: false,
+            osc52: config.clipboard_osc52,
+            paste_command: config.clipboard_paste_command.clone(),
+        }
+    }
+
+    /// keep `text` for pasting and hand it to the terminal's clipboard
+    pub fn copy(&mut self, text: String, block: bool) -> Result<()> {
+        self.text = text;
+        self.block = block;
+        if self.osc52 {
+            Terminal::set_clipboard(&self.text)?;
+        }
+        Ok(())
+    }
+
+    /// add `text` to the end of what was copied last
+    pub fn append(&mut self, text: &str) -> Result<()> {
+        let mut copied = mem::take(&mut self.text);
+        copied.push_str(text);
+        self.copy(copied, false)
+    }
+
+    /// text from the system clipboard helper, or the last copied text when there is none,
+    /// and whether it should be pasted as a rectangle
+    pub fn paste(&self) -> (String, bool) {
+        match self.system_paste() {
+            Some(text) => {
+                let block = self.block && text == self.text;
+                (text, block)
+            }
+            None => (self.text.clone(), self.block),
+        }
+    }
+
+    fn system_paste(&self) -> Option<String> {
+        match self.paste_command.as_deref() {
+            Some("none" | "") => None,
+            Some(command) => run(command),
+            None => PASTE_COMMANDS
+                .iter()
+                .filter(|(var, _)| var.is_empty() || env::var_os(var).is_some())
+                .find_map(|(_, command)| run(command)),
+        }
+    }
+}
+
+/// the output of `command`, run by `sh` so it can quote arguments
+fn run(command: &str) -> Option<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
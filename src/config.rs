@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::Snippet;
+
+/// user settings, read from `$HECTO_CONFIG` or `~/.config/hecto/config`
+///
+/// every line is `key = value`, blank lines and lines starting with `#` are skipped;
+/// settings after a `[filetype]` header, e.g. `[rust]`, only apply to that file type;
+/// `snippet name = body` defines a snippet, see `Snippet` for how bodies are written
+#[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+    /// also copy into the system clipboard with the OSC 52 escape sequence
+    pub clipboard_osc52: bool,
+    /// shell command whose output is pasted, `None` means detect one from the environment
+    pub clipboard_paste_command: Option<String>,
+    /// columns per indentation level and tab stop
+    pub indent_width: usize,
+    /// indent with tabs instead of spaces
+    pub hard_tabs: bool,
+    /// follow the indentation a file already uses instead of the two settings above
+    pub detect_indent: bool,
+    /// opening and closing characters typed in pairs, `None` uses the file type's own
+    pub auto_pairs: Option<String>,
+    /// characters joining what is on either side into one word, e.g. `-` for kebab-case
+    pub word_chars: String,
+    /// the display column paragraphs are filled to
+    pub fill_column: usize,
+    /// break lines at `fill_column` while typing
+    pub auto_fill: bool,
+    /// clean up whitespace when saving, as the settings below say
+    pub cleanup_on_save: bool,
+    /// remove spaces and tabs ending a line when cleaning up
+    pub trim_trailing_whitespace: bool,
+    /// rewrite indentation in the indent style when cleaning up
+    pub fix_indentation: bool,
+    /// collapse blank lines ending the file into its final line break when cleaning up
+    pub trim_trailing_lines: bool,
+    /// clean up every line when saving, not only the ones edited since opening the file
+    pub cleanup_all_lines: bool,
+    /// show words that could finish the one being typed as it is typed
+    pub auto_complete: bool,
+    /// the key bindings: `default`, `emacs`, or `vim` for modal editing
+    pub keymap: String,
+    /// snippet names and bodies, in the order they were defined
+    pub snippets: Vec<(String, String)>,
+    /// `key = value` lines of each `[filetype]` section, by lowercase file type name
+    sections: HashMap<String, Vec<(String, String)>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            clipboard_osc52: true,
+            clipboard_paste_command: None,
+            indent_width: 4,
+            hard_tabs: false,
+            detect_indent: true,
+            auto_pairs: None,
+            word_chars: String::from("_"),
+            fill_column: 80,
+            auto_fill: false,
+            cleanup_on_save: false,
+            trim_trailing_whitespace: true,
+            fix_indentation: true,
+            trim_trailing_lines: true,
+            cleanup_all_lines: false,
+            auto_complete: true,
+            keymap: String::from("default"),
+            snippets: Vec::new(),
+            sections: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// load the config file, collecting a message for every line that could not be applied
+    pub fn load() -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut errors = Vec::new();
+        let Some(path) = Self::path() else {
+            return (config, errors);
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return (config, errors);
+        };
+        let mut section: Option<String> = None;
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = Some(name.trim().to_lowercase());
+                continue;
+            }
+            let applied = match (line.split_once('='), &section) {
+                (Some((key, value)), None) => config.set(key.trim(), value.trim()),
+                (Some((key, value)), Some(section)) => {
+                    let (key, value) = (key.trim().to_string(), value.trim().to_string());
+                    // check it now, so mistakes are reported even for files not opened yet
+                    let checked = Self::default().set(&key, &value);
+                    if checked.is_ok() {
+                        config
+                            .sections
+                            .entry(section.clone())
+                            .or_default()
+                            .push((key, value));
+                    }
+                    checked
+                }
+                (None, _) => Err("expected `key = value`".to_string()),
+            };
+            if let Err(err) = applied {
+                errors.push(format!("{}:{}: {}", path.display(), index + 1, err));
+            }
+        }
+        (config, errors)
+    }
+
+    /// these settings with the `[filetype]` section for `file_type` applied on top
+    pub fn for_file_type(&self, file_type: &str) -> Self {
+        let mut config = self.clone();
+        if let Some(section) = self.sections.get(&file_type.to_lowercase()) {
+            for (key, value) in section {
+                // every line was checked while loading
+                let _ = config.set(key, value);
+            }
+        }
+        config
+    }
+
+    fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("HECTO_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        Some(Self::directory()?.join("config"))
+    }
+
+    /// where the config file and anything else kept between sessions live
+    pub fn directory() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("HECTO_CONFIG") {
+            return PathBuf::from(path).parent().map(PathBuf::from);
+        }
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("hecto"))
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if let Some(name) = key.strip_prefix("snippet ") {
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(format!(
+                    "expected a snippet name without spaces, found `{name}`"
+                ));
+            }
+            value.parse::<Snippet>()?;
+            self.snippets.push((name.to_string(), value.to_string()));
+            return Ok(());
+        }
+        match key {
+            "clipboard_osc52" => self.clipboard_osc52 = parse_bool(value)?,
+            "clipboard_paste_command" => {
+                self.clipboard_paste_command = match value {
+                    "auto" => None,
+                    command => Some(command.to_string()),
+                }
+            }
+            "indent_width" => {
+                self.indent_width = match value.parse() {
+                    Ok(width @ 1..=16) => width,
+                    _ => return Err(format!("expected a width from 1 to 16, found `{value}`")),
+                }
+            }
+            "hard_tabs" => self.hard_tabs = parse_bool(value)?,
+            "detect_indent" => self.detect_indent = parse_bool(value)?,
+            "auto_pairs" => {
+                if !value.chars().count().is_multiple_of(2) {
+                    return Err(format!(
+                        "expected opening and closing characters in pairs, found `{value}`"
+                    ));
+                }
+                self.auto_pairs = Some(value.to_string());
+            }
+            "word_chars" => self.word_chars = value.to_string(),
+            "fill_column" => {
+                self.fill_column = match value.parse() {
+                    Ok(column @ 1..) => column,
+                    _ => return Err(format!("expected a column above 0, found `{value}`")),
+                }
+            }
+            "auto_fill" => self.auto_fill = parse_bool(value)?,
+            "cleanup_on_save" => self.cleanup_on_save = parse_bool(value)?,
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = parse_bool(value)?,
+            "fix_indentation" => self.fix_indentation = parse_bool(value)?,
+            "trim_trailing_lines" => self.trim_trailing_lines = parse_bool(value)?,
+            "cleanup_all_lines" => self.cleanup_all_lines = parse_bool(value)?,
+            "auto_complete" => self.auto_complete = parse_bool(value)?,
+            "keymap" => {
+                if !["default", "emacs", "vim"].contains(&value) {
+                    return Err(format!("expected default, emacs or vim, found `{value}`"));
+                }
+                self.keymap = value.to_string();
+            }
+            _ => return Err(format!("unknown setting `{key}`")),
+        }
+        Ok(())
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("expected true or false, found `{value}`")),
+    }
+}
@@ -1,29 +1,172 @@
+use crate::FileType;
 use crate::Position;
 use crate::Result;
 use crate::Row;
 use crate::SearchDirection;
+use std::collections::VecDeque;
 use std::fs;
 use std::io::Write;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// pairs whose closer dedents a line it is typed on first, and that get split apart by Enter
+const BRACKETS: [(char, char); 3] = [('{', '}'), ('(', ')'), ('[', ']')];
+
+/// how many steps back undo can go
+const UNDO_STEPS: usize = 1000;
+
+/// how one level of indentation is written
+#[derive(Debug, Clone, Copy)]
+pub struct Indent {
+    /// columns per level, which is also where tab stops are
+    pub width: usize,
+    /// indent with tab characters instead of spaces
+    pub hard_tabs: bool,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Self {
+            width: 4,
+            hard_tabs: false,
+        }
+    }
+}
+
+impl Indent {
+    /// the text of one indentation level
+    pub fn unit(self) -> String {
+        if self.hard_tabs {
+            String::from("\t")
+        } else {
+            " ".repeat(self.width)
+        }
+    }
+
+    /// indentation reaching display column `columns`, with as many tabs as fit when
+    /// indenting with tabs
+    pub fn to_column(self, columns: usize) -> String {
+        if self.hard_tabs {
+            "\t".repeat(columns / self.width) + &" ".repeat(columns % self.width)
+        } else {
+            " ".repeat(columns)
+        }
+    }
+
+    /// the style most indented rows use, keeping `self` for anything they don't tell
+    pub fn detected(self, rows: &[Row]) -> Self {
+        let (mut tabs, mut spaces) = (0, 0);
+        // how often each step from 2 to 8 spaces deeper than the previous row shows up
+        let mut steps = [0; 9];
+        let mut previous = 0;
+        for row in rows {
+            let indentation = row.indentation();
+            if indentation.len() == row.as_bytes().len() {
+                continue;
+            }
+            if indentation.starts_with('\t') {
+                tabs += 1;
+                continue;
+            }
+            let depth = indentation.len();
+            if depth > 0 {
+                spaces += 1;
+            }
+            if depth > previous && depth - previous < steps.len() {
+                steps[depth - previous] += 1;
+            }
+            previous = depth;
+        }
+        if tabs > spaces {
+            return Self {
+                hard_tabs: true,
+                ..self
+            };
+        }
+        let width = (2..steps.len())
+            .filter(|&width| steps[width] > 0)
+            .max_by_key(|&width| steps[width]);
+        match width {
+            Some(width) => Self {
+                width,
+                hard_tabs: false,
+            },
+            None => self,
+        }
+    }
+
+    /// how many graphemes at the end of the whitespace `before` make up the last level
+    fn last_level(self, before: &str) -> usize {
+        if before.ends_with('\t') {
+            return 1;
+        }
+        let trailing = before.len() - before.trim_end_matches(' ').len();
+        let column: usize = before.chars().fold(0, |column, c| {
+            if c == '\t' {
+                column + self.width - column % self.width
+            } else {
+                column + 1
+            }
+        });
+        trailing.min((column.saturating_sub(1)) % self.width + 1)
+    }
+}
+
+/// the whitespace a cleanup touches, none by default
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Cleanup {
+    /// remove spaces and tabs ending a row
+    pub trailing_whitespace: bool,
+    /// rewrite indentation in the document's indent style
+    pub indentation: bool,
+    /// remove blank rows ending the document, so the file ends in a single line break
+    pub trailing_lines: bool,
+}
+
+/// the rows an undo step changed as they were before it, and where the cursor was
+struct Change {
+    /// the first row changed
+    start: usize,
+    /// how many rows follow the changed ones, which edits within them leave as it is
+    after: usize,
+    rows: Vec<Row>,
+    cursor: Position,
+}
 
 #[derive(Default)]
 pub struct Document {
     rows: Vec<Row>,
     pub filename: Option<String>,
     dirty: bool,
+    pub indent: Indent,
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    /// where the cursor was when the current step began, until its first change is recorded
+    step: Option<Position>,
+    /// whether the last undo change is the current step's, growing with each edit
+    recording: bool,
+    /// how many edits, undos and redos there have been, to tell whether any happened
+    revision: usize,
 }
 
 impl Document {
     /// read document from file
     pub fn open(filename: &str) -> Result<Self> {
         let contents = fs::read_to_string(filename)?;
-        let rows: Vec<Row> = contents.lines().map(Row::from).collect();
+        let rows: Vec<Row> = contents.lines().map(Row::original).collect();
         Ok(Self {
             rows,
             dirty: false,
             filename: Some(filename.to_string()),
+            ..Self::default()
         })
     }
 
+    /// switch to the indentation style the rows already use, if they show one
+    pub fn detect_indent(&mut self) {
+        self.indent = self.indent.detected(&self.rows);
+    }
+
     /// get document's row from index
     pub fn row(&self, index: usize) -> Option<&Row> {
         self.rows.get(index)
@@ -37,6 +180,10 @@ impl Document {
         self.rows.len()
     }
 
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
     pub fn status_bar_text(&self) -> String {
         let modified_indicator = if self.dirty { " (modified)" } else { "" };
         let file_name = if let Some(ref name) = self.filename {
@@ -49,7 +196,13 @@ impl Document {
         format!("{} - {} lines{}", file_name, self.len(), modified_indicator)
     }
 
-    pub fn insert_newline(&mut self, position: &Position) {
+    pub fn file_type(&self) -> FileType {
+        self.filename
+            .as_deref()
+            .map_or_else(FileType::default, FileType::from)
+    }
+
+    fn split_row(&mut self, position: &Position) {
         let (x, y) = (position.x as usize, position.y as usize);
         if let Some(row) = self.rows.get_mut(y) {
             let new_row = row.split(x);
@@ -60,47 +213,496 @@ impl Document {
         }
     }
 
-    pub fn insert(&mut self, position: &Position, ch: char) {
+    /// break the row at `position`, carrying its indentation over to the new row and adding
+    /// a level after an opener such as `{`; returns where the cursor goes
+    pub fn insert_newline(&mut self, position: &Position) -> Position {
         let (x, y) = (position.x as usize, position.y as usize);
         if y > self.len() {
-            return;
+            return position.clone();
+        }
+        self.modified(y..y + 1);
+        if y == self.len() {
+            self.split_row(position);
+            return Position {
+                x: 0,
+                y: position.y + 1,
+            };
+        }
+        let row = &self.rows[y];
+        let indentation: String = row.indentation().graphemes(true).take(x).collect();
+        let before = row.substring(0, x);
+        let opener = before
+            .trim_end()
+            .chars()
+            .last()
+            .filter(|c| self.file_type().indent_openers().contains(c));
+
+        self.split_row(position);
+        let remainder = self.rows.remove(y + 1);
+        let remainder = remainder.substring(
+            remainder.indentation().graphemes(true).count(),
+            remainder.len(),
+        );
+        let mut new_indentation = indentation.clone();
+        if opener.is_some() {
+            new_indentation.push_str(&self.indent.unit());
+        }
+        let closer = BRACKETS
+            .iter()
+            .find(|(open, _)| Some(*open) == opener)
+            .map(|(_, close)| *close);
+        if closer.is_some() && remainder.starts_with(|c| Some(c) == closer) {
+            // `{|}` becomes three lines, the closer back at the opener's indentation
+            self.rows.insert(
+                y + 1,
+                Row::from(format!("{indentation}{remainder}").as_str()),
+            );
+            self.rows.insert(y + 1, Row::from(new_indentation.as_str()));
+        } else {
+            self.rows.insert(
+                y + 1,
+                Row::from(format!("{new_indentation}{remainder}").as_str()),
+            );
+        }
+        Position {
+            x: new_indentation.graphemes(true).count() as u16,
+            y: position.y + 1,
+        }
+    }
+
+    /// insert `ch` at `position`, returning where the cursor goes
+    ///
+    /// a closing bracket typed on a row holding nothing but indentation takes away one level
+    pub fn insert(&mut self, position: &Position, ch: char) -> Position {
+        let (mut x, y) = (position.x as usize, position.y as usize);
+        if y > self.len() {
+            return position.clone();
         }
-        self.dirty = true;
         if ch == '\n' {
-            self.insert_newline(position);
-            return;
+            return self.insert_newline(position);
+        }
+        if ch == '\t' && !self.indent.hard_tabs {
+            let column = self.rows.get(y).map_or(0, |row| {
+                row.display_column(x.min(row.len()), self.indent.width)
+            });
+            let spaces = " ".repeat(self.indent.width - column % self.indent.width);
+            return self.insert_str(position, &spaces);
         }
+        self.modified(y..y + 1);
+        let dedent = BRACKETS
+            .iter()
+            .any(|&(open, close)| close == ch && self.file_type().indent_openers().contains(&open));
+        let indent = self.indent;
         if let Some(row) = self.rows.get_mut(y) {
+            x = x.min(row.len());
+            let before = row.substring(0, x);
+            let removed = indent.last_level(&before);
+            if dedent && removed > 0 && before.trim_matches([' ', '\t']).is_empty() {
+                row.delete_range(x - removed, x);
+                x -= removed;
+            }
             row.insert(x, ch);
         } else {
             let mut row = Row::default();
-            row.insert(x, ch);
+            row.insert(0, ch);
             self.rows.push(row);
+            x = 0;
+        }
+        Position {
+            x: x as u16 + 1,
+            y: position.y,
+        }
+    }
+
+    /// Backspace within a row's indentation: remove back to the previous indentation level,
+    /// returning where the cursor goes, or `None` when `position` is past the indentation
+    pub fn delete_indent_level(&mut self, position: &Position) -> Option<Position> {
+        let (x, y) = (position.x as usize, position.y as usize);
+        let row = self.rows.get(y)?;
+        let before = row.substring(0, x);
+        if x == 0 || x > row.len() || !before.trim_matches([' ', '\t']).is_empty() {
+            return None;
+        }
+        let removed = self.indent.last_level(&before);
+        if removed == 0 {
+            return None;
+        }
+        self.modified(y..y + 1);
+        self.rows[y].delete_range(x - removed, x);
+        Some(Position {
+            x: (x - removed) as u16,
+            y: position.y,
+        })
+    }
+
+    /// add one indentation level to the start of row `y` unless it is blank,
+    /// returning how many graphemes were inserted
+    pub fn indent_line(&mut self, y: usize) -> usize {
+        let unit = self.indent.unit();
+        let Some(row) = self.rows.get(y) else {
+            return 0;
+        };
+        if row.as_bytes().iter().all(u8::is_ascii_whitespace) {
+            return 0;
+        }
+        self.modified(y..y + 1);
+        self.rows[y].insert_str(0, &unit);
+        unit.len()
+    }
+
+    /// take one indentation level off the start of row `y`, returning how many graphemes were removed
+    pub fn dedent_line(&mut self, y: usize) -> usize {
+        let width = self.indent.width;
+        let Some(row) = self.rows.get(y) else {
+            return 0;
+        };
+        let indentation = row.indentation();
+        let removed = if indentation.starts_with('\t') {
+            1
+        } else {
+            let spaces = indentation.len() - indentation.trim_start_matches(' ').len();
+            spaces.min(width)
+        };
+        if removed > 0 {
+            self.modified(y..y + 1);
+            self.rows[y].delete_range(0, removed);
+        }
+        removed
+    }
+
+    /// insert possibly multi-line `text` at `position`, returning the position after it
+    pub fn insert_str(&mut self, position: &Position, text: &str) -> Position {
+        let (mut x, mut y) = (position.x as usize, position.y as usize);
+        if y > self.len() {
+            return position.clone();
+        }
+        self.modified(y..y + 1);
+        if y == self.len() {
+            self.rows.push(Row::default());
+        }
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.split_row(&Position {
+                    x: x as u16,
+                    y: y as u16,
+                });
+                x = 0;
+                y += 1;
+            }
+            let row = &mut self.rows[y];
+            let before = row.len();
+            row.insert_str(x, line);
+            x += row.len() - before;
+        }
+        Position {
+            x: x as u16,
+            y: y as u16,
+        }
+    }
+
+    /// the text between two positions, rows joined by `\n`
+    pub fn text(&self, start: &Position, end: &Position) -> String {
+        let mut text = String::new();
+        for y in start.y..=end.y {
+            let Some(row) = self.rows.get(y as usize) else {
+                break;
+            };
+            let from = if y == start.y { start.x as usize } else { 0 };
+            let to = if y == end.y {
+                end.x as usize
+            } else {
+                row.len()
+            };
+            if y > start.y {
+                text.push('\n');
+            }
+            text.push_str(&row.substring(from, to));
         }
+        text
     }
 
+    /// put `text` in place of the grapheme at `at`, returning the grapheme it replaced,
+    /// or `None` when there is none to replace at the end of the row
+    pub fn replace_grapheme(&mut self, at: &Position, text: &str) -> Option<String> {
+        let x = at.x as usize;
+        let replaced = self
+            .rows
+            .get(at.y as usize)
+            .filter(|row| x < row.len())?
+            .substring(x, x + 1);
+        self.modified(at.y as usize..at.y as usize + 1);
+        let row = &mut self.rows[at.y as usize];
+        row.delete_range(x, x + 1);
+        row.insert_str(x, text);
+        Some(replaced)
+    }
+
+    /// remove the text between two positions, joining the rows at both ends
+    pub fn delete_range(&mut self, start: &Position, end: &Position) {
+        let (start_y, end_y) = (start.y as usize, end.y as usize);
+        if start_y >= self.len() || (start_y, start.x) >= (end_y, end.x) {
+            return;
+        }
+        self.modified(start_y..end_y + 1);
+        if start_y == end_y {
+            self.rows[start_y].delete_range(start.x as usize, end.x as usize);
+            return;
+        }
+        let mut tail = Row::default();
+        if let Some(last) = self.rows.get_mut(end_y) {
+            tail = last.split(end.x as usize);
+        }
+        self.rows.drain(start_y + 1..=end_y.min(self.len() - 1));
+        let row = &mut self.rows[start_y];
+        row.split(start.x as usize);
+        row.extend(&tail);
+    }
+
+    /// join the row at `position` onto the end of the previous one
     pub fn delete_line(&mut self, position: &Position) {
         let y = position.y as usize;
+        if y == 0 || y >= self.len() {
+            return;
+        }
+        self.modified(y - 1..y + 1);
         let current_row = self.rows.remove(y);
         if let Some(row) = self.rows.get_mut(y - 1) {
             row.extend(&current_row);
         }
     }
 
+    /// swap the rows in `lines` with the row above them, or below when not `up`,
+    /// returning whether there was one
+    pub fn move_lines(&mut self, lines: Range<usize>, up: bool) -> bool {
+        if lines.is_empty() || lines.end > self.len() {
+            return false;
+        }
+        if up && lines.start > 0 {
+            self.modified(lines.start - 1..lines.end);
+            self.rows[lines.start - 1..lines.end].rotate_left(1);
+        } else if !up && lines.end < self.len() {
+            self.modified(lines.start..lines.end + 1);
+            self.rows[lines.start..=lines.end].rotate_right(1);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// insert a copy of the rows in `lines` right after them
+    pub fn duplicate_lines(&mut self, lines: Range<usize>) {
+        if lines.is_empty() || lines.end > self.len() {
+            return;
+        }
+        let copies: Vec<Row> = self.rows[lines.clone()]
+            .iter()
+            .map(|row| Row::from(row.substring(0, row.len()).as_str()))
+            .collect();
+        self.modified(lines.end..lines.end);
+        self.rows.splice(lines.end..lines.end, copies);
+    }
+
+    /// remove the rows in `lines` whole
+    pub fn delete_lines(&mut self, lines: Range<usize>) {
+        let lines = lines.start.min(self.len())..lines.end.min(self.len());
+        if !lines.is_empty() {
+            self.modified(lines.clone());
+            self.rows.drain(lines);
+        }
+    }
+
+    /// swap the rows in `lines` for `texts`, one row each
+    pub fn replace_lines(&mut self, lines: Range<usize>, texts: &[String]) {
+        let lines = lines.start.min(self.len())..lines.end.min(self.len());
+        self.modified(lines.clone());
+        self.rows
+            .splice(lines, texts.iter().map(|text| Row::from(text.as_str())));
+    }
+
+    /// join the rows in `lines` into the first one, the leading whitespace of each
+    /// collapsed to a single space, returning where the last join happened
+    pub fn join_lines(&mut self, lines: Range<usize>) -> Option<Position> {
+        let lines = lines.start..lines.end.min(self.len());
+        if lines.len() < 2 {
+            return None;
+        }
+        self.modified(lines.clone());
+        let joined: Vec<Row> = self.rows.drain(lines.start + 1..lines.end).collect();
+        let row = &mut self.rows[lines.start];
+        let mut at = row.len();
+        for next in joined {
+            let text = next.substring(0, next.len());
+            let text = text.trim_start_matches([' ', '\t']);
+            at = row.len();
+            if !text.is_empty() && !row.is_empty() {
+                row.insert_str(at, " ");
+            }
+            row.insert_str(row.len(), text);
+        }
+        Some(Position {
+            x: at as u16,
+            y: lines.start as u16,
+        })
+    }
+
+    /// delete the grapheme at `position`, or join the next row when at the end of a row
     pub fn delete(&mut self, position: &Position) {
         let (x, y) = (position.x as usize, position.y as usize);
-        if x == 0 {
-            self.delete_line(position);
+        let Some(row) = self.rows.get(y) else {
             return;
+        };
+        if x < row.len() {
+            self.modified(y..y + 1);
+            self.rows[y].delete(x);
+        } else if y + 1 < self.len() {
+            self.delete_line(&Position {
+                x: 0,
+                y: position.y + 1,
+            });
         }
-        if let Some(row) = self.rows.get_mut(y) {
-            row.delete(x);
+    }
+
+    /// start a new undo step: every change until the next call is undone together
+    pub fn begin_step(&mut self, cursor: &Position) {
+        self.step = Some(cursor.clone());
+    }
+
+    /// record the rows in `lines` before they change, as the start of a new undo step
+    /// or added to the current one, then mark the document dirty
+    fn modified(&mut self, lines: Range<usize>) {
+        let len = self.rows.len();
+        let lines = lines.start.min(len)..lines.end.min(len);
+        match self.undo.back_mut() {
+            Some(change) if self.recording && self.step.is_none() => {
+                // rows around the step's change are still as they were before it
+                let end = len - change.after;
+                if lines.start < change.start {
+                    let before = self.rows[lines.start..change.start].iter().cloned();
+                    change.rows.splice(0..0, before);
+                    change.start = lines.start;
+                }
+                if lines.end > end {
+                    change.rows.extend_from_slice(&self.rows[end..lines.end]);
+                    change.after = len - lines.end;
+                }
+            }
+            _ => {
+                // a change outside any step is recorded all the same, to keep the
+                // steps before it lined up with the rows
+                let cursor = self.step.take().unwrap_or(Position {
+                    x: 0,
+                    y: lines.start as u16,
+                });
+                if self.undo.len() == UNDO_STEPS {
+                    self.undo.pop_front();
+                }
+                self.undo.push_back(Change {
+                    start: lines.start,
+                    after: len - lines.end,
+                    rows: self.rows[lines].to_vec(),
+                    cursor,
+                });
+                self.redo.clear();
+                self.recording = true;
+            }
         }
+        self.dirty = true;
+        self.revision += 1;
     }
 
-    /// save doc into disk
-    pub fn save(&mut self) -> Result<()> {
-        if let Some(ref filename) = self.filename {
+    /// swap the rows `change` holds back in, returning the change that undoes that
+    /// with `cursor`, and where the cursor was in `change`
+    fn swap_in(&mut self, change: Change, cursor: &Position) -> (Change, Position) {
+        let Change {
+            start,
+            after,
+            rows,
+            cursor: then,
+        } = change;
+        let end = self.rows.len() - after;
+        let rows = self.rows.splice(start..end, rows).collect();
+        self.recording = false;
+        self.dirty = true;
+        self.revision += 1;
+        let swapped = Change {
+            start,
+            after,
+            rows,
+            cursor: cursor.clone(),
+        };
+        (swapped, then)
+    }
+
+    /// go back to before the last step, returning where the cursor was then
+    pub fn undo(&mut self, cursor: &Position) -> Option<Position> {
+        let change = self.undo.pop_back()?;
+        let (redo, then) = self.swap_in(change, cursor);
+        self.redo.push(redo);
+        Some(then)
+    }
+
+    /// apply the last undone step again, returning where the cursor was after it
+    pub fn redo(&mut self, cursor: &Position) -> Option<Position> {
+        let change = self.redo.pop()?;
+        let (undo, then) = self.swap_in(change, cursor);
+        self.undo.push_back(undo);
+        Some(then)
+    }
+
+    /// clean up the whitespace of the rows edited since the file was read, or of every
+    /// row with `all_lines`, returning how many rows changed
+    pub fn cleanup_whitespace(&mut self, cleanup: Cleanup, all_lines: bool) -> usize {
+        let touched = |row: &Row| all_lines || row.is_edited();
+        let mut cleaned: Vec<(usize, String)> = Vec::new();
+        for (y, row) in self.rows.iter().enumerate() {
+            if !touched(row) {
+                continue;
+            }
+            let text = row.substring(0, row.len());
+            let mut clean = text.as_str();
+            if cleanup.trailing_whitespace {
+                clean = clean.trim_end_matches([' ', '\t']);
+            }
+            let rest = clean.trim_start_matches([' ', '\t']);
+            let clean = if cleanup.indentation && !rest.is_empty() {
+                let indentation = Row::from(&clean[..clean.len() - rest.len()]);
+                let columns = indentation.width(self.indent.width);
+                format!("{}{rest}", self.indent.to_column(columns))
+            } else {
+                clean.to_string()
+            };
+            if clean != text {
+                cleaned.push((y, clean));
+            }
+        }
+        let mut keep = self.rows.len();
+        if cleanup.trailing_lines {
+            while keep > 0 && touched(&self.rows[keep - 1]) {
+                let row = &self.rows[keep - 1];
+                if !row.substring(0, row.len()).trim().is_empty() {
+                    break;
+                }
+                keep -= 1;
+            }
+        }
+        let changed = cleaned.iter().filter(|&&(y, _)| y < keep).count() + self.len() - keep;
+        if changed == 0 {
+            return 0;
+        }
+        let first = cleaned.first().map_or(keep, |&(y, _)| y.min(keep));
+        self.modified(first..self.len());
+        for (y, clean) in cleaned {
+            self.rows[y] = Row::from(clean.as_str());
+        }
+        self.rows.truncate(keep);
+        changed
+    }
+
+    /// save doc into disk, first cleaning up the whitespace of edited rows, or of every
+    /// row with `all_lines`
+    pub fn save(&mut self, cleanup: Cleanup, all_lines: bool) -> Result<()> {
+        if let Some(filename) = self.filename.clone() {
+            self.cleanup_whitespace(cleanup, all_lines);
             let mut file = fs::File::create(filename)?;
             for row in &self.rows {
                 file.write_all(row.as_bytes())?;
@@ -115,6 +717,74 @@ impl Document {
         self.dirty
     }
 
+    /// where the bracket matching the one at `at` is, looking no further than `rows`
+    ///
+    /// brackets inside strings and comments still count, there is no highlighting yet
+    /// to tell them apart
+    pub fn matching_bracket(&self, at: &Position, rows: Range<usize>) -> Option<Position> {
+        let (x, y) = (at.x as usize, at.y as usize);
+        let bracket = self.rows.get(y)?.graphemes().nth(x)?;
+        let (open, close, forward) = BRACKETS.iter().find_map(|&(open, close)| {
+            if bracket.starts_with(open) {
+                Some((open, close, true))
+            } else if bracket.starts_with(close) {
+                Some((open, close, false))
+            } else {
+                None
+            }
+        })?;
+        let (same, other) = if forward {
+            (open, close)
+        } else {
+            (close, open)
+        };
+        let mut depth = 0;
+        let mut check = |grapheme: &str, x: usize, y: usize| {
+            if grapheme.starts_with(same) {
+                depth += 1;
+            } else if grapheme.starts_with(other) {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(Position {
+                        x: x as u16,
+                        y: y as u16,
+                    });
+                }
+            }
+            None
+        };
+        if forward {
+            self.rows
+                .iter()
+                .enumerate()
+                .take(rows.end)
+                .skip(y)
+                .find_map(|(row_y, row)| {
+                    let skip = if row_y == y { x } else { 0 };
+                    row.graphemes()
+                        .enumerate()
+                        .skip(skip)
+                        .find_map(|(row_x, grapheme)| check(grapheme, row_x, row_y))
+                })
+        } else {
+            self.rows
+                .iter()
+                .enumerate()
+                .take(y + 1)
+                .skip(rows.start)
+                .rev()
+                .find_map(|(row_y, row)| {
+                    let take = if row_y == y { x + 1 } else { row.len() };
+                    let graphemes: Vec<&str> = row.graphemes().take(take).collect();
+                    graphemes
+                        .into_iter()
+                        .enumerate()
+                        .rev()
+                        .find_map(|(row_x, grapheme)| check(grapheme, row_x, row_y))
+                })
+        }
+    }
+
     pub fn find(
         &self,
         query: &str,
@@ -124,49 +794,38 @@ impl Document {
         if after.y as usize >= self.rows.len() {
             return None;
         }
-        let (mut position_x, mut position_y) = (after.x as usize, after.y as usize);
-
-        let start = if direaction == SearchDirection::Forward {
-            after.y as usize
-        } else {
-            0
+        let found = |(y, row, at): (usize, &Row, usize)| {
+            row.find(query, at, direaction).map(|x| Position {
+                x: x as u16,
+                y: y as u16,
+            })
         };
-        let end = if direaction == SearchDirection::Forward {
-            self.rows.len()
+        let after_y = after.y as usize;
+        if direaction == SearchDirection::Forward {
+            self.rows
+                .iter()
+                .enumerate()
+                .skip(after_y)
+                .map(|(y, row)| (y, row, if y == after_y { after.x as usize } else { 0 }))
+                .find_map(found)
         } else {
-            after.y.saturating_add(1) as usize
-        };
-
-        for _ in start..end {
-            if let Some(row) = self.rows.get(position_y) {
-                if let Some(x) = row.find(query, position_x, direaction) {
-                    position_x = x;
-                    return Some(Position {
-                        x: position_x as u16,
-                        y: position_y as u16,
-                    });
-                }
-                if direaction == SearchDirection::Forward {
-                    position_y = after.y.saturating_add(1) as usize;
-                    position_x = 0;
-                } else {
-                    position_y = after.y.saturating_sub(1) as usize;
-                    position_x = self.rows[position_y].len();
-                }
-            } else {
-                return None;
-            }
-        }
-        // let mut x = after.x as usize;
-        // for (j, row) in self.rows.iter().enumerate().skip(after.y as usize) {
-        //     if let Some(i) = row.find(query, x) {
-        //         return Some(Position {
-        //             x: i.try_into().unwrap(),
-        //             y: j.try_into().unwrap(),
-        //         });
-        //     }
-        //     x = 0
-        // }
-        None
+            self.rows
+                .iter()
+                .enumerate()
+                .take(after_y + 1)
+                .rev()
+                .map(|(y, row)| {
+                    (
+                        y,
+                        row,
+                        if y == after_y {
+                            after.x as usize
+                        } else {
+                            row.len()
+                        },
+                    )
+                })
+                .find_map(found)
+        }
     }
 }
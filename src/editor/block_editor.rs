@@ -0,0 +1,174 @@
+use super::Cursor;
+use super::Position;
+use crate::Editor;
+use crate::Key;
+use crate::Row;
+
+/// a rectangle of display columns `left..right` over the rows `top..=bottom`
+pub(super) struct Block {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl Editor {
+    /// the display column `position` sits at, tabs and wide characters included
+    pub(super) fn display_column(&self, position: &Position) -> usize {
+        let x = position.x as usize;
+        self.document
+            .row(position.y as usize)
+            .map_or(x, |row| row.display_column(x, self.document.indent.width))
+    }
+
+    /// the rectangle spanned by the block anchor and the cursor
+    pub(super) fn block_range(&self) -> Option<Block> {
+        let anchor = self.block.as_ref()?;
+        let cursor = &self.cursor_position;
+        let (anchor_column, cursor_column) =
+            (self.display_column(anchor), self.display_column(cursor));
+        Some(Block {
+            top: anchor.y.min(cursor.y) as usize,
+            bottom: anchor.y.max(cursor.y) as usize,
+            left: anchor_column.min(cursor_column),
+            right: anchor_column.max(cursor_column),
+        })
+    }
+
+    /// the graphemes of `row` inside the block
+    pub(super) fn block_columns(&self, block: &Block, row: &Row) -> (usize, usize) {
+        let tab_width = self.document.indent.width;
+        (
+            row.index_at_column(block.left, tab_width),
+            row.index_at_column(block.right, tab_width),
+        )
+    }
+
+    /// move the cursor while keeping the opposite corner of the block in place
+    pub(super) fn block_move(&mut self, key: Key) {
+        if self.block.is_none() {
+            self.block = Some(self.cursor_position.clone());
+            self.selection = None;
+            self.cursors.clear();
+        }
+        self.move_cursor(key);
+    }
+
+    /// the block's text, one line per row
+    pub(super) fn block_text(&self, block: &Block) -> String {
+        (block.top..=block.bottom)
+            .map(|y| {
+                self.document.row(y).map_or(String::new(), |row| {
+                    let (from, to) = self.block_columns(block, row);
+                    row.substring(from, to)
+                })
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// remove the block's text from every row and put the cursor at its top-left corner
+    pub(super) fn delete_block(&mut self, block: &Block) {
+        for y in block.top..=block.bottom {
+            let Some(row) = self.document.row(y) else {
+                break;
+            };
+            let (from, to) = self.block_columns(block, row);
+            self.document.delete_range(
+                &Position {
+                    x: from as u16,
+                    y: y as u16,
+                },
+                &Position {
+                    x: to as u16,
+                    y: y as u16,
+                },
+            );
+        }
+        self.block = None;
+        let x = self.document.row(block.top).map_or(0, |row| {
+            row.index_at_column(block.left, self.document.indent.width)
+        });
+        self.cursor_position = Position {
+            x: x as u16,
+            y: block.top as u16,
+        };
+        self.scroll();
+    }
+
+    /// type, Backspace or Delete over the block: its text is cleared, then unless that
+    /// was all a Backspace or Delete had to do, `key` is applied at the block's left edge
+    /// on every row long enough to reach it
+    pub(super) fn edit_block(&mut self, key: Key) {
+        let Some(block) = self.block_range() else {
+            return;
+        };
+        let cursor_y = self.cursor_position.y as usize;
+        if block.left < block.right {
+            self.delete_block(&block);
+            if !matches!(key, Key::Char(_)) {
+                return;
+            }
+        }
+        self.block = None;
+        let mut cursors: Vec<Position> = (block.top..=block.bottom)
+            .filter_map(|y| {
+                let row = self.document.row(y)?;
+                if row.width(self.document.indent.width) < block.left {
+                    return None;
+                }
+                Some(Position {
+                    x: row.index_at_column(block.left, self.document.indent.width) as u16,
+                    y: y as u16,
+                })
+            })
+            .collect();
+        let Some(primary) = cursors
+            .iter()
+            .position(|position| position.y as usize == cursor_y)
+            .or((!cursors.is_empty()).then_some(0))
+        else {
+            return;
+        };
+        self.cursor_position = cursors.remove(primary);
+        self.cursors = cursors
+            .into_iter()
+            .map(|position| Cursor {
+                position,
+                anchor: None,
+            })
+            .collect();
+        self.edit_at_cursors(key);
+    }
+
+    /// insert the lines of `text` one below the other, all starting at the cursor's column
+    pub(super) fn paste_block(&mut self, text: &str) {
+        let column = self.display_column(&self.cursor_position);
+        let mut end = self.cursor_position.clone();
+        for (index, line) in text.split('\n').enumerate() {
+            let y = self.cursor_position.y + index as u16;
+            if y as usize >= self.document.len() {
+                self.document.insert_str(
+                    &Position {
+                        x: 0,
+                        y: self.document.len() as u16,
+                    },
+                    "",
+                );
+            }
+            let (len, width) = self.document.row(y as usize).map_or((0, 0), |row| {
+                (row.len(), row.width(self.document.indent.width))
+            });
+            if width < column {
+                self.document
+                    .insert_str(&Position { x: len as u16, y }, &" ".repeat(column - width));
+            }
+            let x = self.document.row(y as usize).map_or(0, |row| {
+                row.index_at_column(column, self.document.indent.width)
+            });
+            end = self.document.insert_str(&Position { x: x as u16, y }, line);
+        }
+        self.cursor_position = end;
+        self.scroll();
+    }
+}
@@ -0,0 +1,27 @@
+use super::Position;
+use crate::Editor;
+use std::ops::Range;
+
+impl Editor {
+    /// the bracket under or just before the cursor, and the one matching it within `rows`
+    pub(super) fn bracket_pair(&self, rows: Range<usize>) -> Option<(Position, Position)> {
+        let Position { x, y } = self.cursor_position;
+        let under = self.cursor_position.clone();
+        let before = (x > 0).then(|| Position { x: x - 1, y });
+        [Some(under), before]
+            .into_iter()
+            .flatten()
+            .find_map(|bracket| {
+                let found = self.document.matching_bracket(&bracket, rows.clone())?;
+                Some((bracket, found))
+            })
+    }
+
+    /// put the cursor on the bracket matching the one under or just before it
+    pub(super) fn jump_to_bracket(&mut self) {
+        if let Some((_, found)) = self.bracket_pair(0..self.document.len()) {
+            self.cursor_position = found;
+            self.scroll();
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::mem;
+
+use super::Position;
+use super::StatusMessage;
+use crate::Editor;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// the cases text can be converted to, by the name the prompt takes
+const CASES: [&str; 7] = [
+    "upper", "lower", "title", "snake", "camel", "pascal", "kebab",
+];
+
+impl Editor {
+    /// ask for a case and convert the selection, or the word at the cursor, to it
+    pub(super) fn convert_case(&mut self) {
+        let prompt = format!("Convert to case ({}): ", CASES.join(", "));
+        let Ok(Some(name)) = self.prompt(&prompt, |_, _, _| {}) else {
+            return;
+        };
+        let name = name.trim().to_lowercase();
+        let Some(case) = CASES.iter().find(|case| case.starts_with(&name)) else {
+            self.status_message = StatusMessage::from(format!("Unknown case {name}."));
+            return;
+        };
+        let Some((start, end)) = self.selection_range().or_else(|| self.word_range()) else {
+            return;
+        };
+        let text = self.document.text(&start, &end);
+        let converted = convert(&text, case);
+        if converted == text {
+            return;
+        }
+        self.document.delete_range(&start, &end);
+        let end = self.document.insert_str(&start, &converted);
+        if self.selection.is_some() {
+            self.selection = Some(start);
+        }
+        self.cursor_position = end;
+        self.cursors.clear();
+        self.scroll();
+    }
+
+    /// the bounds of the word the cursor is in or touching
+    fn word_range(&self) -> Option<(Position, Position)> {
+        let Position { x, y } = self.cursor_position;
+        let row = self.document.row(y as usize)?;
+        let at = x as usize;
+        let (start, end) = row
+            .words(&self.settings().word_chars)
+            .into_iter()
+            .find(|&(start, end)| start <= at && at <= end)?;
+        Some((
+            Position { x: start as u16, y },
+            Position { x: end as u16, y },
+        ))
+    }
+}
+
+/// `text` in one of `CASES`; the identifier cases rebuild every line from its words,
+/// keeping the whitespace around it
+fn convert(text: &str, case: &str) -> String {
+    match case {
+        "upper" => text.to_uppercase(),
+        "lower" => text.to_lowercase(),
+        "title" => text.split_word_bounds().map(capitalized).collect(),
+        _ => text
+            .split('\n')
+            .map(|line| {
+                let body = line.trim();
+                let start = line.len() - line.trim_start().len();
+                let words = identifier_words(body);
+                let joined = match case {
+                    "snake" => lowercased(&words).join("_"),
+                    "kebab" => lowercased(&words).join("-"),
+                    "pascal" => words.iter().map(|word| capitalized(word)).collect(),
+                    _ => words
+                        .iter()
+                        .enumerate()
+                        .map(|(index, word)| {
+                            if index == 0 {
+                                word.to_lowercase()
+                            } else {
+                                capitalized(word)
+                            }
+                        })
+                        .collect(),
+                };
+                format!("{}{joined}{}", &line[..start], &line[start + body.len()..])
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+    }
+}
+
+/// the words of an identifier, split at anything but letters and digits and where the
+/// case changes: `parseHTTPResponse2` is `parse`, `HTTP`, `Response2`
+fn identifier_words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (index, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(mem::take(&mut word));
+            }
+            continue;
+        }
+        let previous = index.checked_sub(1).map(|index| chars[index]);
+        let next = chars.get(index + 1);
+        let boundary = c.is_uppercase()
+            && previous.is_some_and(|previous| {
+                previous.is_lowercase()
+                    || previous.is_numeric()
+                    || (previous.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+            });
+        if boundary && !word.is_empty() {
+            words.push(mem::take(&mut word));
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn lowercased(words: &[String]) -> Vec<String> {
+    words.iter().map(|word| word.to_lowercase()).collect()
+}
+
+/// the first letter of `word` in upper case, the rest in lower case
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or(String::new(), |first| {
+        first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect()
+    })
+}
@@ -0,0 +1,176 @@
+use super::Position;
+use super::StatusMessage;
+use crate::Command;
+use crate::Editor;
+use crate::Key;
+use crate::Registers;
+use crate::Result;
+use crate::Row;
+
+impl Editor {
+    /// the selected text, or the whole current line when nothing is selected
+    fn copied_text(&self) -> (String, Position, Position) {
+        if let Some((start, end)) = self.selection_range() {
+            return (self.document.text(&start, &end), start, end);
+        }
+        let y = self.cursor_position.y;
+        let len = self.document.row(y as usize).map_or(0, Row::len);
+        let start = Position { x: 0, y };
+        let end = Position { x: len as u16, y };
+        let mut text = self.document.text(&start, &end);
+        text.push('\n');
+        (text, start, Position { x: 0, y: y + 1 })
+    }
+
+    /// keep `text` in register `register`, or on the clipboard without one, saying what
+    /// went wrong after `done`, e.g. `Copied`
+    pub(super) fn store(&mut self, text: String, block: bool, register: Option<char>, done: &str) {
+        let Some(register) = register else {
+            if self.clipboard.copy(text, block).is_err() {
+                self.status_message = StatusMessage::from(format!(
+                    "{done}, but could not reach the system clipboard."
+                ));
+            }
+            return;
+        };
+        self.registers.set(register, &text);
+        if self.registers.save().is_err() {
+            self.status_message =
+                StatusMessage::from(format!("{done}, but could not save the registers."));
+        }
+    }
+
+    /// copy the selection or the current line to the clipboard, or to `register`
+    pub(super) fn copy(&mut self, register: Option<char>) {
+        if let Some(block) = self.block_range() {
+            self.store(self.block_text(&block), true, register, "Copied");
+        } else {
+            let (text, _, _) = self.copied_text();
+            self.store(text, false, register, "Copied");
+        }
+    }
+
+    /// cut the selection or the current line to the clipboard, or to `register`
+    pub(super) fn cut(&mut self, register: Option<char>) {
+        if let Some(block) = self.block_range() {
+            self.store(self.block_text(&block), true, register, "Cut");
+            self.delete_block(&block);
+            return;
+        }
+        let (text, start, mut end) = self.copied_text();
+        if end.y as usize >= self.document.len() && end.x == 0 && end.y > start.y {
+            // the last line has no newline after it to take along
+            end = Position {
+                x: self.document.row(start.y as usize).map_or(0, Row::len) as u16,
+                y: start.y,
+            };
+        }
+        self.store(text, false, register, "Cut");
+        self.document.delete_range(&start, &end);
+        self.cursor_position = start;
+        self.selection = None;
+        self.scroll();
+    }
+
+    /// paste over the selection from the clipboard, or from `register`
+    pub(super) fn paste(&mut self, register: Option<char>) {
+        let (text, block) = if let Some(register) = register {
+            let Some(text) = self.registers.get(register) else {
+                self.status_message = StatusMessage::from(format!("Register {register} is empty."));
+                return;
+            };
+            (text.to_string(), false)
+        } else {
+            self.clipboard.paste()
+        };
+        if let Some(block) = self.block_range() {
+            self.delete_block(&block);
+        }
+        self.delete_selection();
+        let text = text.replace("\r\n", "\n");
+        if block {
+            self.paste_block(&text);
+            return;
+        }
+        self.cursor_position = self.document.insert_str(&self.cursor_position, &text);
+        self.scroll();
+    }
+
+    /// ask for a register, uppercase letters too unless `to_paste`, `None` when cancelled
+    fn read_register(&mut self, prompt: &str, to_paste: bool) -> Result<Option<char>> {
+        self.status_message = StatusMessage::from(format!("{prompt} (Esc to cancel): "));
+        self.refresh_screen()?;
+        let key = self.read_key()?;
+        self.status_message = StatusMessage::from(String::new());
+        let name = match key {
+            Key::Char(c) if Registers::is_name(c) || (c.is_ascii_uppercase() && !to_paste) => c,
+            Key::Esc => return Ok(None),
+            key => {
+                self.status_message = StatusMessage::from(format!("{key} is not a register."));
+                return Ok(None);
+            }
+        };
+        Ok(Some(name))
+    }
+
+    /// copy, or cut, to a register asked for, `A` to `Z` adding to `a` to `z`
+    pub(super) fn copy_to_register(&mut self, cut: bool) -> Result<()> {
+        let verb = if cut { "Cut" } else { "Copy" };
+        if let Some(register) = self.read_register(&format!("{verb} to register"), false)? {
+            if cut {
+                self.cut(Some(register));
+            } else {
+                self.copy(Some(register));
+            }
+        }
+        Ok(())
+    }
+
+    /// paste from a register asked for
+    pub(super) fn paste_from_register(&mut self) -> Result<()> {
+        if let Some(register) = self.read_register("Paste from register", true)? {
+            self.paste(Some(register));
+        }
+        Ok(())
+    }
+
+    /// show every register, pasting the one picked
+    pub(super) fn list_registers(&mut self) -> Result<()> {
+        let (names, lines): (Vec<char>, Vec<String>) =
+            self.registers.describe().into_iter().unzip();
+        if names.is_empty() {
+            self.status_message = StatusMessage::from(String::from("The registers are empty."));
+            return Ok(());
+        }
+        if let Some(index) = self.pick_from_list("Registers", lines)? {
+            self.paste(Some(names[index]));
+        }
+        Ok(())
+    }
+
+    /// cut from the cursor to the end of the line, or the line break when already there;
+    /// kills right after one another add up to a single clipboard entry
+    pub(super) fn kill_line(&mut self) {
+        let start = self.cursor_position.clone();
+        let len = self.document.row(start.y as usize).map_or(0, Row::len) as u16;
+        let end = if start.x < len {
+            Position { x: len, y: start.y }
+        } else {
+            self.next_position(&start)
+        };
+        let text = self.document.text(&start, &end);
+        let copied = if self.last_command == Some(Command::KillLine) {
+            self.clipboard.append(&text)
+        } else {
+            self.clipboard.copy(text, false)
+        };
+        if copied.is_err() {
+            self.status_message =
+                StatusMessage::from("Cut, but could not reach the system clipboard.".to_string());
+        }
+        self.document.delete_range(&start, &end);
+        self.selection = None;
+        self.mark = false;
+        self.scroll();
+    }
+}
@@ -0,0 +1,93 @@
+use super::Position;
+use super::StatusMessage;
+use crate::Editor;
+use crate::Row;
+use unicode_segmentation::UnicodeSegmentation;
+
+impl Editor {
+    /// comment out the current lines with the file type's comment syntax, the markers
+    /// lined up at their smallest indentation, or uncomment them when every one that
+    /// isn't blank already is
+    pub(super) fn toggle_comment(&mut self) {
+        let file_type = self.document.file_type();
+        let Some((open, close)) = file_type.comment() else {
+            self.status_message =
+                StatusMessage::from(format!("{} has no comments.", file_type.name()));
+            return;
+        };
+        // with the indentation, all spaces and tabs, as long in bytes as in graphemes
+        let lines: Vec<(usize, String, usize)> = self
+            .current_lines()
+            .filter_map(|y| {
+                let row = self.document.row(y)?;
+                let text = row.substring(0, row.len());
+                (!text.trim().is_empty()).then(|| (y, text, row.indentation().len()))
+            })
+            .collect();
+        let commented = |text: &str| {
+            let text = text.trim_matches([' ', '\t']);
+            text.starts_with(open) && text.ends_with(close)
+        };
+        if lines.iter().all(|(_, text, _)| commented(text)) {
+            for (y, text, indent) in &lines {
+                let indent = *indent;
+                let rest = text[indent..].trim_end_matches([' ', '\t']);
+                let body = rest.strip_prefix(open).unwrap_or(rest);
+                let body = body.strip_prefix(' ').unwrap_or(body);
+                let removed = rest[..rest.len() - body.len()].graphemes(true).count();
+                let body = body.strip_suffix(close).unwrap_or(body);
+                let body = if close.is_empty() {
+                    body
+                } else {
+                    body.strip_suffix(' ').unwrap_or(body)
+                };
+                self.replace_line(*y, &format!("{}{}", &text[..indent], body));
+                self.shift_on_line(*y, indent, 0, removed);
+            }
+        } else {
+            let at = lines
+                .iter()
+                .map(|&(_, _, indent)| indent)
+                .min()
+                .unwrap_or(0);
+            for (y, text, _) in &lines {
+                let mut commented = format!("{}{open} {}", &text[..at], &text[at..]);
+                if !close.is_empty() {
+                    commented = format!("{commented} {close}");
+                }
+                self.replace_line(*y, &commented);
+                self.shift_on_line(*y, at, open.graphemes(true).count() + 1, 0);
+            }
+        }
+        self.cursors.clear();
+        self.scroll();
+    }
+
+    /// swap the text of row `y` for `text`
+    fn replace_line(&mut self, y: usize, text: &str) {
+        let len = self.document.row(y).map_or(0, Row::len);
+        let (start, end) = (
+            Position { x: 0, y: y as u16 },
+            Position {
+                x: len as u16,
+                y: y as u16,
+            },
+        );
+        self.document.delete_range(&start, &end);
+        self.document.insert_str(&start, text);
+    }
+
+    /// move the cursor and the end of its selection on row `y` along with `inserted`
+    /// graphemes put in, or `removed` ones taken out, at `at`
+    fn shift_on_line(&mut self, y: usize, at: usize, inserted: usize, removed: usize) {
+        let len = self.document.row(y).map_or(0, Row::len);
+        for position in [Some(&mut self.cursor_position), self.selection.as_mut()]
+            .into_iter()
+            .flatten()
+            .filter(|position| position.y as usize == y && position.x as usize >= at)
+        {
+            let x = (position.x as usize + inserted).saturating_sub(removed);
+            position.x = x.max(at).min(len) as u16;
+        }
+    }
+}
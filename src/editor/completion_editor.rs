@@ -0,0 +1,178 @@
+use super::Position;
+use super::StatusMessage;
+use crate::Command;
+use crate::Editor;
+use crate::Key;
+use crate::Lookup;
+use std::collections::HashMap;
+
+/// how many candidates the popup shows at once
+pub(super) const POPUP_HEIGHT: usize = 8;
+
+/// how long a word typed has to be before candidates pop up on their own
+const AUTO_COMPLETE_LENGTH: usize = 3;
+
+/// words that could finish the one being typed, shown in a popup under it
+pub(super) struct Completion {
+    /// where the word being completed starts
+    pub start: Position,
+    /// the candidates found when the popup opened, which typing on narrows down
+    words: Vec<String>,
+    /// best first
+    pub candidates: Vec<String>,
+    /// none while the popup only shows the candidates, as one that opened on its own does
+    pub selected: Option<usize>,
+}
+
+impl Editor {
+    /// open the popup with words finishing the one before the cursor, or with
+    /// `automatic`, only once it is long enough and quietly when there are none;
+    /// an open popup for the same word keeps its candidates, narrowed down
+    pub(super) fn complete(&mut self, automatic: bool) {
+        let previous = self.completion.take();
+        let missed = self.completion_miss.take();
+        if !self.cursors.is_empty() || self.block.is_some() {
+            return;
+        }
+        let Position { x, y } = self.cursor_position;
+        let word_chars = self.settings().word_chars.clone();
+        let Some((start, end)) = self.document.row(y as usize).and_then(|row| {
+            row.words(&word_chars)
+                .into_iter()
+                .find(|&(_, end)| end == x as usize)
+        }) else {
+            if !automatic {
+                self.status_message =
+                    StatusMessage::from(String::from("No word to complete before the cursor."));
+            }
+            return;
+        };
+        let previous = previous
+            .filter(|completion| completion.start.y == y && completion.start.x as usize == start);
+        if automatic && previous.is_none() && end - start < AUTO_COMPLETE_LENGTH {
+            return;
+        }
+        let start = Position { x: start as u16, y };
+        let revision = self.document.revision();
+        // a word nothing finished a keystroke ago has no more candidates for a letter more
+        if automatic
+            && previous.is_none()
+            && missed.is_some_and(|(at, missed)| at == start && missed + 1 == revision)
+        {
+            self.completion_miss = Some((start, revision));
+            return;
+        }
+        let prefix = self.document.text(&start, &self.cursor_position);
+        let (words, picked) = match previous {
+            Some(completion) => (completion.words, completion.selected.is_some()),
+            None => (self.candidates(&prefix, y as usize, &word_chars), false),
+        };
+        let candidates: Vec<String> = words
+            .iter()
+            .filter(|word| word.len() > prefix.len() && word.starts_with(&prefix))
+            .cloned()
+            .collect();
+        if candidates.is_empty() {
+            if automatic {
+                self.completion_miss = Some((start, revision));
+            } else {
+                self.status_message =
+                    StatusMessage::from(format!("No words start with `{prefix}`."));
+            }
+            return;
+        }
+        self.completion = Some(Completion {
+            start,
+            words,
+            candidates,
+            selected: (picked || !automatic).then_some(0),
+        });
+    }
+
+    /// the words in the document longer than `prefix` that start with it, those used
+    /// near line `y` and often first
+    fn candidates(&self, prefix: &str, y: usize, word_chars: &str) -> Vec<String> {
+        // how often each word shows up and how far its nearest use is from `y`
+        let mut found: HashMap<String, (usize, usize)> = HashMap::new();
+        for index in 0..self.document.len() {
+            let Some(row) = self.document.row(index) else {
+                continue;
+            };
+            for (start, end) in row.words(word_chars) {
+                let is_typed = index == y && end == self.cursor_position.x as usize;
+                let word = row.substring(start, end);
+                if is_typed || word.len() <= prefix.len() || !word.starts_with(prefix) {
+                    continue;
+                }
+                let distance = index.abs_diff(y);
+                let (count, nearest) = found.entry(word).or_insert((0, distance));
+                *count += 1;
+                *nearest = (*nearest).min(distance);
+            }
+        }
+        let mut candidates: Vec<(String, (usize, usize))> = found.into_iter().collect();
+        // a word used twice as often ranks like one twice as near
+        candidates.sort_by(|(a, (a_count, a_nearest)), (b, (b_count, b_nearest))| {
+            (a_nearest * b_count)
+                .cmp(&(b_nearest * a_count))
+                .then(b_count.cmp(a_count))
+                .then(a.cmp(b))
+        });
+        candidates.into_iter().map(|(word, _)| word).collect()
+    }
+
+    /// handle `key` while the popup is open, going by the command it is bound to: a popup
+    /// that opened on its own only shows the candidates until `complete` picks the first
+    /// one, then moving up and down picks another, Tab or Enter puts it in and `cancel`
+    /// closes the popup; typing is left for the caller with the popup open, for
+    /// `complete` to narrow down, and any other key closes it, but is left for the
+    /// caller too; returns whether the key was used up
+    pub(super) fn completion_key(&mut self, key: Key) -> bool {
+        let command = match self.keymap.lookup(&[key]) {
+            Lookup::Command(command) => Some(command),
+            _ => None,
+        };
+        let Some(completion) = &mut self.completion else {
+            return false;
+        };
+        let count = completion.candidates.len();
+        match (key, command, completion.selected) {
+            (_, Some(Command::Complete), None) => completion.selected = Some(0),
+            (_, Some(Command::Move(Key::Down)), Some(selected)) => {
+                completion.selected = Some((selected + 1) % count);
+            }
+            (_, Some(Command::Move(Key::Up)), Some(selected)) => {
+                completion.selected = Some((selected + count - 1) % count);
+            }
+            (Key::Char('\n'), _, Some(_)) | (_, Some(Command::Indent), Some(_)) => {
+                self.accept_completion();
+            }
+            (_, Some(Command::Cancel), Some(_)) => self.completion = None,
+            (Key::Char(c), None, _) if !c.is_control() => return false,
+            _ => {
+                self.completion = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// replace the word being completed with the selected candidate
+    fn accept_completion(&mut self) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        let Some(word) = completion
+            .selected
+            .and_then(|selected| completion.candidates.get(selected))
+        else {
+            return;
+        };
+        self.document.begin_step(&self.cursor_position);
+        self.document
+            .delete_range(&completion.start, &self.cursor_position);
+        self.cursor_position = self.document.insert_str(&completion.start, word);
+        self.typing = false;
+        self.scroll();
+    }
+}
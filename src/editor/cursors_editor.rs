@@ -0,0 +1,242 @@
+use super::selection_editor::{ordered, shifted};
+use super::Cursor;
+use super::Position;
+use super::SearchDirection;
+use super::StatusMessage;
+use crate::Editor;
+use crate::Key;
+use crate::Row;
+use unicode_segmentation::UnicodeSegmentation;
+
+impl Editor {
+    /// apply a typed character, Backspace or Delete at every cursor
+    ///
+    /// cursors are handled from the end of the document backwards, so an edit never moves
+    /// a cursor still waiting its turn; those already handled are shifted past each edit
+    pub(super) fn edit_at_cursors(&mut self, key: Key) {
+        let mut pending: Vec<(Cursor, bool)> = self
+            .cursors
+            .drain(..)
+            .map(|cursor| (cursor, false))
+            .collect();
+        let primary = Cursor {
+            position: self.cursor_position.clone(),
+            anchor: self.selection.take(),
+        };
+        pending.push((primary, true));
+        pending.sort_by(|(a, _), (b, _)| b.position.cmp(&a.position));
+
+        let mut done: Vec<(Position, bool)> = Vec::new();
+        for (cursor, is_primary) in pending {
+            let mut position = cursor.position.clone();
+            if let Some((start, end)) = cursor
+                .anchor
+                .as_ref()
+                .and_then(|anchor| ordered(anchor, &cursor.position))
+            {
+                // a selection is replaced by what is typed, or just removed
+                self.document.delete_range(&start, &end);
+                for (other, _) in &mut done {
+                    *other = shifted(other, &end, &start);
+                }
+                self.shift_tab_stops(&end, &start);
+                position = start;
+                if !matches!(key, Key::Char(_)) {
+                    done.push((position, is_primary));
+                    continue;
+                }
+            }
+            self.cursor_position = position;
+            let (end, new_end) = self.edit(key);
+            for (other, _) in &mut done {
+                *other = shifted(other, &end, &new_end);
+            }
+            self.shift_tab_stops(&end, &new_end);
+            done.push((self.cursor_position.clone(), is_primary));
+        }
+
+        for (position, is_primary) in done {
+            if is_primary {
+                self.cursor_position = position;
+            } else {
+                self.cursors.push(Cursor {
+                    position,
+                    anchor: None,
+                });
+            }
+        }
+        self.merge_cursors();
+        self.scroll();
+    }
+
+    /// the position just past the grapheme or line break Delete would remove
+    pub(super) fn next_position(&self, position: &Position) -> Position {
+        let len = self.document.row(position.y as usize).map_or(0, Row::len);
+        if (position.x as usize) < len {
+            Position {
+                x: position.x + 1,
+                y: position.y,
+            }
+        } else if (position.y as usize) + 1 < self.document.len() {
+            Position {
+                x: 0,
+                y: position.y + 1,
+            }
+        } else {
+            position.clone()
+        }
+    }
+
+    pub(super) fn move_cursors(&mut self, key: Key) {
+        for index in 0..self.cursors.len() {
+            let position = self.moved(&self.cursors[index].position, key);
+            self.cursors[index] = Cursor {
+                position,
+                anchor: None,
+            };
+        }
+        self.move_cursor(key);
+        self.merge_cursors();
+    }
+
+    /// drop extra cursors that ended up on the same spot as another one
+    fn merge_cursors(&mut self) {
+        self.cursors.sort_by(|a, b| a.position.cmp(&b.position));
+        self.cursors.dedup_by(|a, b| a.position == b.position);
+        let primary = self.cursor_position.clone();
+        self.cursors.retain(|cursor| cursor.position != primary);
+    }
+
+    /// make the current primary cursor an extra one and put the primary at `cursor`
+    fn push_primary(&mut self, cursor: Cursor) {
+        let previous = Cursor {
+            position: self.cursor_position.clone(),
+            anchor: self.selection.take(),
+        };
+        self.cursors.push(previous);
+        self.cursor_position = cursor.position;
+        self.selection = cursor.anchor;
+        self.merge_cursors();
+        self.scroll();
+    }
+
+    /// add a cursor on the row above the top-most cursor, or below the bottom-most one
+    pub(super) fn add_cursor_vertically(&mut self, key: Key) {
+        let all = self
+            .cursors
+            .iter()
+            .map(|cursor| &cursor.position)
+            .chain([&self.cursor_position]);
+        let edge = if key == Key::Up {
+            all.min_by_key(|position| position.y)
+        } else {
+            all.max_by_key(|position| position.y)
+        };
+        let Some(edge) = edge.cloned() else {
+            return;
+        };
+        let mut position = self.moved(&edge, key);
+        if position.y == edge.y || position.y as usize >= self.document.len() {
+            return;
+        }
+        let len = self.document.row(position.y as usize).map_or(0, Row::len);
+        position.x = position.x.min(len as u16);
+        self.push_primary(Cursor {
+            position,
+            anchor: None,
+        });
+    }
+
+    /// select the word under the cursor, or once something is selected,
+    /// add a cursor selecting the next occurrence of it
+    pub(super) fn add_cursor_at_next_match(&mut self) {
+        let Some((start, end)) = self
+            .selection_range()
+            .filter(|(start, end)| start.y == end.y)
+        else {
+            let Position { x, y } = self.cursor_position;
+            if let Some(row) = self.document.row(y as usize) {
+                let (from, to) = row.word_at(x as usize, &self.settings().word_chars);
+                if from < to {
+                    self.selection = Some(Position { x: from as u16, y });
+                    self.cursor_position = Position { x: to as u16, y };
+                }
+            }
+            return;
+        };
+        let query = self.document.text(&start, &end);
+        let found = self
+            .document
+            .find(&query, &end, SearchDirection::Forward)
+            .or_else(|| {
+                self.document
+                    .find(&query, &Position::default(), SearchDirection::Forward)
+            });
+        let Some(found) = found else {
+            return;
+        };
+        let cursor = Cursor {
+            position: Position {
+                x: found.x + (end.x - start.x),
+                y: found.y,
+            },
+            anchor: Some(found),
+        };
+        let taken = cursor.position == self.cursor_position
+            || self
+                .cursors
+                .iter()
+                .any(|other| other.position == cursor.position);
+        if taken {
+            self.status_message =
+                StatusMessage::from(format!("Every occurrence of {query} has a cursor."));
+        } else {
+            self.push_primary(cursor);
+        }
+    }
+
+    /// put a cursor at every match of the selected text, or of the word at the cursor
+    pub(super) fn add_cursors_at_every_match(&mut self) {
+        let query = if let Some((start, end)) = self.selection_range() {
+            self.document.text(&start, &end)
+        } else {
+            let Position { x, y } = self.cursor_position;
+            let Some(row) = self.document.row(y as usize) else {
+                return;
+            };
+            let (from, to) = row.word_at(x as usize, &self.settings().word_chars);
+            row.substring(from, to)
+        };
+        if !query.is_empty() {
+            self.add_cursors_at_matches(&query);
+        }
+    }
+
+    /// put a cursor selecting every match of `query`, the first one being the primary
+    pub(super) fn add_cursors_at_matches(&mut self, query: &str) {
+        let len = query.graphemes(true).count() as u16;
+        let mut matches = Vec::new();
+        let mut after = Position::default();
+        while let Some(found) = self.document.find(query, &after, SearchDirection::Forward) {
+            after = Position {
+                x: found.x + len,
+                y: found.y,
+            };
+            matches.push(Cursor {
+                position: after.clone(),
+                anchor: Some(found),
+            });
+        }
+        if matches.is_empty() {
+            self.status_message = StatusMessage::from(format!("Not found :{query}."));
+            self.macros.failed = true;
+            return;
+        }
+        self.status_message = StatusMessage::from(format!("{} cursors.", matches.len()));
+        let first = matches.remove(0);
+        self.cursor_position = first.position;
+        self.selection = first.anchor;
+        self.cursors = matches;
+        self.scroll();
+    }
+}
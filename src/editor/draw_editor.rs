@@ -1,6 +1,8 @@
 use std::time::Duration;
 use std::time::Instant;
 
+use super::completion_editor::POPUP_HEIGHT;
+use super::Listing;
 use super::Position;
 use crate::Editor;
 use crate::Result;
@@ -12,6 +14,16 @@ const STATUS_FG_COLOR: color::Rgb = color::Rgb(63, 63, 63);
 const STATUS_BG_COLOR: color::Rgb = color::Rgb(239, 239, 239);
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// a box drawn over the text area, such as the completion popup
+struct Overlay {
+    /// the terminal column and row of its top left corner
+    x: usize,
+    y: usize,
+    width: usize,
+    /// each line padded to `width`, with whether it is highlighted
+    lines: Vec<(String, bool)>,
+}
+
 impl Editor {
     pub fn refresh_screen(&self) -> Result<()> {
         Terminal::cursor_hide();
@@ -26,7 +38,8 @@ impl Editor {
             self.draw_status_bar();
             self.draw_message_bar();
             Terminal::cursor_position(&Position {
-                x: self.cursor_position.x.saturating_sub(self.offset.x),
+                x: (self.display_column(&self.cursor_position) as u16)
+                    .saturating_sub(self.offset.x),
                 y: self.cursor_position.y.saturating_sub(self.offset.y),
             });
         }
@@ -37,26 +50,181 @@ impl Editor {
 
     fn draw_rows(&self) {
         let height = self.terminal.size().height;
+        if let Some(listing) = &self.listing {
+            self.draw_listing(listing);
+            return;
+        }
+        // only a match on screen gets highlighted, so there is no need to look further
+        let top = self.offset.y as usize;
+        let brackets = self.bracket_pair(top..top + height as usize);
+        let overlay = self.completion_overlay();
         for terminal_row in 0..height {
             Terminal::clear_current_line();
-            if let Some(row) = self.document.row((terminal_row + self.offset.y) as usize) {
-                self.draw_row(row);
+            let index = (terminal_row + self.offset.y) as usize;
+            let overlay_line = overlay.as_ref().and_then(|overlay| {
+                let line = overlay
+                    .lines
+                    .get((terminal_row as usize).checked_sub(overlay.y)?)?;
+                Some((overlay, line))
+            });
+            if let Some(row) = self.document.row(index) {
+                let brackets: Vec<usize> = brackets
+                    .iter()
+                    .flat_map(|(bracket, found)| [bracket, found])
+                    .filter(|position| position.y as usize == index)
+                    .map(|position| position.x as usize)
+                    .collect();
+                self.draw_row(row, index, &brackets, overlay_line);
             } else if terminal_row == height / 3 && self.document.is_empty() {
                 self.draw_welcome();
+            } else if overlay_line.is_some() {
+                self.draw_row(&Row::from("~"), index, &[], overlay_line);
             } else {
                 println!("~\r");
             }
         }
     }
 
+    /// the row, with the line of an overlay covering the columns under it
+    fn draw_row(
+        &self,
+        row: &Row,
+        index: usize,
+        brackets: &[usize],
+        overlay_line: Option<(&Overlay, &(String, bool))>,
+    ) {
+        let start = self.offset.x as usize;
+        let end = self.terminal.size().width as usize + start;
+        let Some((overlay, (line, highlighted))) = overlay_line else {
+            self.draw_columns(row, index, brackets, start, end);
+            println!("\r");
+            return;
+        };
+        let (left, right) = (start + overlay.x, start + overlay.x + overlay.width);
+        self.draw_columns(row, index, brackets, start, left);
+        let drawn = row.width(self.document.indent.width).clamp(start, left);
+        print!("{}", " ".repeat(left - drawn));
+        if *highlighted {
+            Terminal::set_invert();
+        } else {
+            Terminal::set_bg_color(STATUS_BG_COLOR);
+            Terminal::set_fg_color(STATUS_FG_COLOR);
+        }
+        print!("{line}");
+        Terminal::reset_style();
+        Terminal::reset_bg_color();
+        Terminal::reset_fg_color();
+        self.draw_columns(row, index, brackets, right, end);
+        println!("\r");
+    }
+
     // start            end
     // | ............... |
     //     row's width
-    fn draw_row(&self, row: &Row) {
-        let start = self.offset.x as usize;
-        let end = self.terminal.size().width as usize + start;
-        let row = row.render(start, end);
-        println!("{}\r", row);
+    //
+    // selections are drawn inverted, the bracket pair at the cursor underlined
+    fn draw_columns(&self, row: &Row, index: usize, brackets: &[usize], start: usize, end: usize) {
+        let tab_width = self.document.indent.width;
+        let mut highlights: Vec<(usize, usize, bool)> = self
+            .selected_columns(index, row.len())
+            .into_iter()
+            .map(|(from, to)| (from, to, true))
+            .chain(brackets.iter().map(|&x| (x, x + 1, false)))
+            .collect();
+        highlights.sort_unstable();
+        let mut at = start;
+        for (from, to, selected) in highlights {
+            let (from, to) = (
+                row.display_column(from, tab_width),
+                row.display_column(to, tab_width),
+            );
+            let (from, to) = (from.clamp(at, end), to.clamp(at, end));
+            if from == to {
+                continue;
+            }
+            print!("{}", row.render(at, from, tab_width));
+            if selected {
+                Terminal::set_invert();
+            } else {
+                Terminal::set_underline();
+            }
+            if from >= row.width(tab_width) {
+                // an extra cursor past the end of the row
+                print!(" ");
+            } else {
+                print!("{}", row.render(from, to, tab_width));
+            }
+            Terminal::reset_style();
+            at = to;
+        }
+        print!("{}", row.render(at, end, tab_width));
+    }
+
+    /// the completion popup, under the word being completed, or over it when there is
+    /// no room below
+    fn completion_overlay(&self) -> Option<Overlay> {
+        let completion = self.completion.as_ref()?;
+        let size = self.terminal.size();
+        let (width, height) = (size.width as usize, size.height as usize);
+        let shown = completion.candidates.len().min(POPUP_HEIGHT);
+        // scrolled just enough to keep the selected candidate in view
+        let first = (completion.selected.unwrap_or(0) + 1).saturating_sub(shown);
+        let candidates = &completion.candidates[first..first + shown];
+        let tab_width = self.document.indent.width;
+        let box_width = candidates
+            .iter()
+            .map(|word| Row::from(word.as_str()).width(tab_width) + 2)
+            .max()?
+            .min(width);
+        let lines = candidates
+            .iter()
+            .enumerate()
+            .map(|(index, word)| {
+                let text = Row::from(format!(" {word} ").as_str()).render(0, box_width, tab_width);
+                let padding = " ".repeat(box_width - Row::from(text.as_str()).width(tab_width));
+                (
+                    format!("{text}{padding}"),
+                    Some(first + index) == completion.selected,
+                )
+            })
+            .collect();
+        let cursor_y = self.cursor_position.y.saturating_sub(self.offset.y) as usize;
+        let y = if cursor_y + 1 + shown <= height {
+            cursor_y + 1
+        } else {
+            cursor_y.saturating_sub(shown)
+        };
+        let x = self
+            .display_column(&completion.start)
+            .saturating_sub(self.offset.x as usize)
+            .min(width - box_width);
+        Some(Overlay {
+            x,
+            y,
+            width: box_width,
+            lines,
+        })
+    }
+
+    fn draw_listing(&self, listing: &Listing) {
+        let width = self.terminal.size().width as usize;
+        let height = self.terminal.size().height as usize;
+        for index in listing.offset..listing.offset + height {
+            Terminal::clear_current_line();
+            if let Some(line) = listing.lines.get(index) {
+                let line: String = line.chars().take(width).collect();
+                if listing.selected == Some(index) {
+                    Terminal::set_invert();
+                    print!("{line}");
+                    Terminal::reset_style();
+                    println!("\r");
+                } else {
+                    println!("{line}\r");
+                }
+            } else {
+                println!("~\r");
+            }
+        }
     }
 
     fn draw_welcome(&self) {
@@ -72,9 +240,15 @@ impl Editor {
 
     fn draw_status_bar(&self) {
         let mut status = self.document.status_bar_text();
+        if let Some(mode) = self.vim_mode_name() {
+            status = format!("-- {mode} -- {status}");
+        }
         let width = self.terminal.size().width as usize;
+        let recording = if self.is_recording() { "REC | " } else { "" };
+        let overwriting = if self.is_overwriting() { "OVR | " } else { "" };
         let line_indicator = format!(
-            "{}/{}",
+            "{overwriting}{recording}{} | {}/{}",
+            self.document.file_type().name(),
             self.cursor_position.y.saturating_add(1),
             self.document.len(),
         );
@@ -0,0 +1,172 @@
+use super::lines_editor::spanned_lines;
+use super::Position;
+use super::StatusMessage;
+use crate::Editor;
+use crate::Row;
+use std::ops::Range;
+
+/// comment and quote markers kept at the start of every line of a filled paragraph
+const FILL_MARKERS: [&str; 5] = ["///", "//!", "//", "#", ">"];
+
+impl Editor {
+    /// re-wrap the paragraph at the cursor, or every paragraph the selection touches,
+    /// to `fill_column`, each keeping its first line's indentation and markers
+    pub(super) fn fill_paragraph(&mut self) {
+        let lines = if self.selection.is_some() {
+            spanned_lines(&self.cursor_position, self.selection.as_ref())
+        } else {
+            self.paragraph_at(self.cursor_position.y as usize)
+        };
+        if lines.is_empty() {
+            self.status_message = StatusMessage::from(String::from("No paragraph to fill."));
+            self.macros.failed = true;
+            return;
+        }
+        let settings = self.settings();
+        let tab_width = self.document.indent.width;
+        let texts: Vec<String> = lines
+            .clone()
+            .filter_map(|y| self.document.row(y))
+            .map(|row| row.substring(0, row.len()))
+            .collect();
+        let mut filled: Vec<String> = Vec::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        for text in texts.iter().map(String::as_str).chain([""]) {
+            let prefix = line_prefix(text);
+            let starts_another = paragraph
+                .first()
+                .is_some_and(|first| line_prefix(first).trim() != prefix.trim());
+            let blank = text[prefix.len()..].trim().is_empty();
+            if (blank || starts_another) && !paragraph.is_empty() {
+                filled.extend(fill(&paragraph, settings.fill_column, tab_width));
+                paragraph.clear();
+            }
+            if blank {
+                filled.push(text.to_string());
+            } else {
+                paragraph.push(text);
+            }
+        }
+        // the empty line chained on to end the last paragraph
+        filled.pop();
+        let last = lines.start + filled.len() - 1;
+        let x = filled
+            .last()
+            .map_or(0, |line| Row::from(line.as_str()).len());
+        self.document.replace_lines(lines, &filled);
+        self.selection = None;
+        self.cursor_position = Position {
+            x: x as u16,
+            y: last as u16,
+        };
+        self.scroll();
+    }
+
+    /// the lines around `y` with text after the same markers, none when `y` is blank
+    fn paragraph_at(&self, y: usize) -> Range<usize> {
+        let text = |y: usize| self.document.row(y).map(|row| row.substring(0, row.len()));
+        let Some(line) = text(y) else {
+            return y..y;
+        };
+        let marker = line_prefix(&line).trim().to_string();
+        let belongs = |y: usize| {
+            text(y).is_some_and(|line| {
+                let prefix = line_prefix(&line);
+                prefix.trim() == marker && !line[prefix.len()..].trim().is_empty()
+            })
+        };
+        if !belongs(y) {
+            return y..y;
+        }
+        let start = (0..y).rev().take_while(|&y| belongs(y)).last().unwrap_or(y);
+        let end = (y + 1..self.document.len())
+            .take_while(|&y| belongs(y))
+            .last()
+            .unwrap_or(y);
+        start..end + 1
+    }
+
+    /// with `auto_fill` on, break the line at the last space that keeps it within
+    /// `fill_column` once typing goes past it
+    pub(super) fn auto_fill(&mut self) {
+        let settings = self.settings();
+        if !settings.auto_fill {
+            return;
+        }
+        let Position { x, y } = self.cursor_position;
+        let tab_width = self.document.indent.width;
+        let Some(row) = self.document.row(y as usize) else {
+            return;
+        };
+        if row.display_column(x as usize, tab_width) <= settings.fill_column {
+            return;
+        }
+        let text = row.substring(0, row.len());
+        let prefix = line_prefix(&text).to_string();
+        let graphemes: Vec<&str> = row.graphemes().collect();
+        let content_start = Row::from(prefix.as_str()).len();
+        let Some(space) = (content_start..x as usize).rev().find(|&index| {
+            graphemes[index] == " " && row.display_column(index, tab_width) <= settings.fill_column
+        }) else {
+            return;
+        };
+        let head_end = (content_start..space)
+            .rev()
+            .find(|&index| graphemes[index] != " ")
+            .map_or(content_start, |index| index + 1);
+        let tail_start = (space..graphemes.len())
+            .find(|&index| graphemes[index] != " ")
+            .unwrap_or(graphemes.len());
+        let head = graphemes[..head_end].concat();
+        let tail = format!("{prefix}{}", graphemes[tail_start..].concat());
+        let y = y as usize;
+        self.document.replace_lines(y..y + 1, &[head, tail]);
+        self.cursor_position = Position {
+            x: (content_start + (x as usize).saturating_sub(tail_start)) as u16,
+            y: y as u16 + 1,
+        };
+        self.scroll();
+    }
+}
+
+/// the indentation and comment or quote markers `line` starts with, with the
+/// whitespace after them
+fn line_prefix(line: &str) -> &str {
+    let mut rest = line.trim_start_matches([' ', '\t']);
+    while let Some(marker) = FILL_MARKERS
+        .iter()
+        .find(|marker| rest.starts_with(**marker))
+    {
+        rest = rest[marker.len()..].trim_start_matches([' ', '\t']);
+    }
+    &line[..line.len() - rest.len()]
+}
+
+/// the words of `lines` wrapped to `width` display columns, behind the first line's
+/// prefix; a word longer than that gets a line of its own
+fn fill(lines: &[&str], width: usize, tab_width: usize) -> Vec<String> {
+    let prefix = line_prefix(lines[0]);
+    let prefix_width = Row::from(prefix).width(tab_width);
+    let words = lines
+        .iter()
+        .flat_map(|line| line[line_prefix(line).len()..].split_whitespace());
+    let mut filled = Vec::new();
+    let mut line = String::new();
+    let mut column = prefix_width;
+    for word in words {
+        let word_width = Row::from(word).width(tab_width);
+        if !line.is_empty() && column + 1 + word_width > width {
+            filled.push(format!("{prefix}{line}"));
+            line.clear();
+            column = prefix_width;
+        }
+        if !line.is_empty() {
+            line.push(' ');
+            column += 1;
+        }
+        line.push_str(word);
+        column += word_width;
+    }
+    filled.push(format!("{prefix}{line}"));
+    filled
+}
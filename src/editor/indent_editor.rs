@@ -0,0 +1,44 @@
+use super::lines_editor::spanned_lines;
+use super::Position;
+use crate::Editor;
+
+impl Editor {
+    /// indent, or dedent, every row touched by a cursor or its selection
+    pub(super) fn shift_lines(&mut self, indent: bool) {
+        let mut spans: Vec<(Position, Option<Position>)> = self
+            .cursors
+            .iter()
+            .map(|cursor| (cursor.position.clone(), cursor.anchor.clone()))
+            .collect();
+        spans.push((self.cursor_position.clone(), self.selection.clone()));
+        let mut rows: Vec<usize> = Vec::new();
+        for (position, anchor) in &spans {
+            rows.extend(spanned_lines(position, anchor.as_ref()));
+        }
+        rows.sort_unstable();
+        rows.dedup();
+
+        for y in rows {
+            let shift = if indent {
+                self.document.indent_line(y)
+            } else {
+                self.document.dedent_line(y)
+            };
+            let positions = self
+                .cursors
+                .iter_mut()
+                .flat_map(|cursor| [Some(&mut cursor.position), cursor.anchor.as_mut()])
+                .chain([Some(&mut self.cursor_position), self.selection.as_mut()])
+                .flatten()
+                .filter(|position| position.y as usize == y);
+            for position in positions {
+                position.x = if indent && position.x > 0 {
+                    position.x + shift as u16
+                } else {
+                    position.x.saturating_sub(shift as u16)
+                };
+            }
+        }
+        self.scroll();
+    }
+}
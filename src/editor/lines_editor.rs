@@ -0,0 +1,213 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Position;
+use super::StatusMessage;
+use crate::Editor;
+use crate::Row;
+
+impl Editor {
+    /// the rows of the primary cursor and its selection
+    pub(super) fn current_lines(&self) -> Range<usize> {
+        let lines = spanned_lines(&self.cursor_position, self.selection.as_ref());
+        lines.start.min(self.document.len())..lines.end.min(self.document.len())
+    }
+
+    /// move the current lines one row up or down, the cursor and selection with them
+    pub(super) fn move_lines(&mut self, up: bool) {
+        if !self.document.move_lines(self.current_lines(), up) {
+            return;
+        }
+        self.cursors.clear();
+        for position in [Some(&mut self.cursor_position), self.selection.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            position.y = if up { position.y - 1 } else { position.y + 1 };
+        }
+        self.scroll();
+    }
+
+    /// insert a copy of the current lines below them and move onto the copy
+    pub(super) fn duplicate_lines(&mut self) {
+        let lines = self.current_lines();
+        self.document.duplicate_lines(lines.clone());
+        self.cursors.clear();
+        for position in [Some(&mut self.cursor_position), self.selection.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            position.y += lines.len() as u16;
+        }
+        self.scroll();
+    }
+
+    /// delete the current lines whole, leaving the cursor on the row that follows them
+    pub(super) fn delete_lines(&mut self) {
+        let lines = self.current_lines();
+        self.document.delete_lines(lines.clone());
+        self.cursors.clear();
+        self.selection = None;
+        let y = lines.start.min(self.document.len().saturating_sub(1));
+        let len = self.document.row(y).map_or(0, Row::len);
+        self.cursor_position = Position {
+            x: self.cursor_position.x.min(len as u16),
+            y: y as u16,
+        };
+        self.scroll();
+    }
+
+    /// ask how, then sort, dedupe, reverse or shuffle the selected lines, or every line
+    /// when nothing is selected
+    pub(super) fn sort_lines(&mut self) {
+        let prompt =
+            "Sort lines (lexical, numeric, natural or nocase, then unique, reverse, shuffle): ";
+        let Ok(Some(options)) = self.prompt(prompt, |_, _, _| {}) else {
+            return;
+        };
+        let lines = if self.selection.is_some() {
+            self.current_lines()
+        } else {
+            0..self.document.len()
+        };
+        let mut texts: Vec<String> = lines
+            .clone()
+            .filter_map(|y| self.document.row(y))
+            .map(|row| row.substring(0, row.len()))
+            .collect();
+        for option in options.split_whitespace() {
+            let compare: fn(&String, &String) -> Ordering = match option {
+                "lexical" => Ord::cmp,
+                "numeric" => |a, b| leading_number(a).total_cmp(&leading_number(b)),
+                "natural" => |a, b| natural_cmp(a, b),
+                "nocase" => |a, b| a.to_lowercase().cmp(&b.to_lowercase()),
+                "unique" => {
+                    // a sort can leave copies apart, between lines it finds equal
+                    let mut seen = HashSet::new();
+                    texts.retain(|text| seen.insert(text.clone()));
+                    continue;
+                }
+                "reverse" => {
+                    texts.reverse();
+                    continue;
+                }
+                "shuffle" => {
+                    shuffle(&mut texts);
+                    continue;
+                }
+                _ => {
+                    self.status_message = StatusMessage::from(format!("Unknown option {option}."));
+                    return;
+                }
+            };
+            texts.sort_by(compare);
+        }
+        let removed = lines.len() - texts.len();
+        self.document.replace_lines(lines.clone(), &texts);
+        self.status_message = StatusMessage::from(format!(
+            "{} lines, {removed} duplicates removed.",
+            texts.len()
+        ));
+        self.cursors.clear();
+        self.selection = None;
+        let y = (self.cursor_position.y as usize).min(lines.start + texts.len().saturating_sub(1));
+        let len = self.document.row(y).map_or(0, Row::len);
+        self.cursor_position = Position {
+            x: self.cursor_position.x.min(len as u16),
+            y: y as u16,
+        };
+        self.scroll();
+    }
+
+    /// join the selected lines, or the current line and the next, into one
+    pub(super) fn join_lines(&mut self) {
+        let mut lines = self.current_lines();
+        if lines.len() < 2 {
+            lines.end = lines.start + 2;
+        }
+        if let Some(position) = self.document.join_lines(lines) {
+            self.cursors.clear();
+            self.selection = None;
+            self.cursor_position = position;
+            self.scroll();
+        }
+    }
+}
+
+/// the rows from `position` to `anchor`, where a selection ending at the start of
+/// a row doesn't take that row along
+pub(super) fn spanned_lines(position: &Position, anchor: Option<&Position>) -> Range<usize> {
+    let anchor = anchor.unwrap_or(position);
+    let (start, end) = (position.min(anchor), position.max(anchor));
+    let last = if end.x == 0 && end.y > start.y {
+        end.y - 1
+    } else {
+        end.y
+    };
+    start.y as usize..last as usize + 1
+}
+
+/// the number a line starts with, zero when there is none
+fn leading_number(text: &str) -> f64 {
+    let text = text.trim_start();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+')))
+        .unwrap_or(text.len());
+    (1..=end)
+        .rev()
+        .find_map(|end| text[..end].parse().ok())
+        .unwrap_or(0.0)
+}
+
+/// compare with runs of digits ordered by their value, so `file9` comes before `file10`
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (a_digits, b_digits) = (digits(a), digits(b));
+            let (a_value, b_value) = (
+                a_digits.trim_start_matches('0'),
+                b_digits.trim_start_matches('0'),
+            );
+            let ordering = (a_value.len().cmp(&b_value.len())).then(a_value.cmp(b_value));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            a = &a[a_digits.len()..];
+            b = &b[b_digits.len()..];
+        } else {
+            if x != y {
+                return x.cmp(&y);
+            }
+            a = &a[x.len_utf8()..];
+            b = &b[y.len_utf8()..];
+        }
+    }
+}
+
+fn digits(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// put `texts` in a random order, seeded from the clock
+fn shuffle(texts: &mut [String]) {
+    let mut state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(1, |elapsed| elapsed.as_nanos() as u64)
+        | 1;
+    for index in (1..texts.len()).rev() {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        texts.swap(index, (state % (index as u64 + 1)) as usize);
+    }
+}
@@ -0,0 +1,95 @@
+use super::StatusMessage;
+use crate::Editor;
+use crate::Key;
+use crate::Result;
+
+/// lines shown over the text area in place of the document
+pub(super) struct Listing {
+    pub lines: Vec<String>,
+    /// the first line on screen
+    pub offset: usize,
+    /// the highlighted line, when one is being picked
+    pub selected: Option<usize>,
+}
+
+impl Editor {
+    /// show `lines` over the text area until a key other than the ones scrolling them
+    pub(super) fn show_list(&mut self, title: &str, lines: Vec<String>) -> Result<()> {
+        let height = self.terminal.size().height as usize;
+        let last = lines.len().saturating_sub(height);
+        self.listing = Some(Listing {
+            lines,
+            offset: 0,
+            selected: None,
+        });
+        self.status_message = StatusMessage::from(format!(
+            "{title} (arrows to scroll, any other key to close)"
+        ));
+        loop {
+            self.refresh_screen()?;
+            let key = self.read_key()?;
+            let Some(listing) = &mut self.listing else {
+                break;
+            };
+            listing.offset = match key {
+                Key::Up => listing.offset.saturating_sub(1),
+                Key::Down => listing.offset + 1,
+                Key::PageUp => listing.offset.saturating_sub(height),
+                Key::PageDown => listing.offset + height,
+                Key::Home => 0,
+                Key::End => last,
+                _ => break,
+            }
+            .min(last);
+        }
+        self.listing = None;
+        self.status_message = StatusMessage::from(String::new());
+        Ok(())
+    }
+
+    /// show `lines` over the text area with one highlighted, returning the one Enter
+    /// picks, or `None` when another key closes them
+    pub(super) fn pick_from_list(
+        &mut self,
+        title: &str,
+        lines: Vec<String>,
+    ) -> Result<Option<usize>> {
+        let height = self.terminal.size().height as usize;
+        let last = lines.len().saturating_sub(1);
+        self.listing = Some(Listing {
+            lines,
+            offset: 0,
+            selected: Some(0),
+        });
+        self.status_message = StatusMessage::from(format!(
+            "{title} (arrows to move, Enter to pick, any other key to close)"
+        ));
+        let picked = loop {
+            self.refresh_screen()?;
+            let key = self.read_key()?;
+            let Some(listing) = &mut self.listing else {
+                break None;
+            };
+            let selected = listing.selected.unwrap_or(0);
+            let selected = match key {
+                Key::Char('\n') => break Some(selected),
+                Key::Up => selected.saturating_sub(1),
+                Key::Down => selected + 1,
+                Key::PageUp => selected.saturating_sub(height),
+                Key::PageDown => selected + height,
+                Key::Home => 0,
+                Key::End => last,
+                _ => break None,
+            }
+            .min(last);
+            // scrolled just enough to keep the highlighted line on screen
+            listing.offset = listing
+                .offset
+                .clamp((selected + 1).saturating_sub(height), selected);
+            listing.selected = Some(selected);
+        };
+        self.listing = None;
+        self.status_message = StatusMessage::from(String::new());
+        Ok(picked)
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+
+use super::StatusMessage;
+use crate::Config;
+use crate::Editor;
+use crate::Key;
+use crate::Result;
+use crate::Terminal;
+
+/// the most rounds `*` plays a macro that never fails
+const UNTIL_FAILURE_ROUNDS: usize = 10_000;
+
+/// recording and playing back keyboard macros
+#[derive(Default)]
+pub(super) struct Macros {
+    /// the keys read so far while recording
+    recording: Option<Vec<Key>>,
+    /// the last recorded macro
+    last: Vec<Key>,
+    /// keys of a macro being played, read before the terminal
+    playback: VecDeque<Key>,
+    playing: bool,
+    /// set by a command that failed, which stops playback
+    pub failed: bool,
+}
+
+impl Editor {
+    /// the next key, from a macro being played or else the terminal, recording
+    /// it when a macro is being recorded; prompts read their input here too
+    pub(super) fn read_key(&mut self) -> Result<Key> {
+        if let Some(key) = self.macros.playback.pop_front() {
+            return Ok(key);
+        }
+        let key = Terminal::read_key()?;
+        if let Some(recording) = &mut self.macros.recording {
+            recording.push(key);
+        }
+        Ok(key)
+    }
+
+    /// have `keys` read next, as if typed
+    pub(super) fn queue_keys(&mut self, keys: &[Key]) {
+        self.macros.playback.extend(keys.iter().copied());
+    }
+
+    pub(super) fn is_recording(&self) -> bool {
+        self.macros.recording.is_some()
+    }
+
+    /// start recording a macro, or stop and offer to save it under a name
+    pub(super) fn toggle_recording(&mut self) {
+        if self.macros.playing {
+            return;
+        }
+        let Some(mut keys) = self.macros.recording.take() else {
+            self.macros.recording = Some(Vec::new());
+            self.status_message =
+                StatusMessage::from(String::from("Recording a macro, Alt-R to stop."));
+            return;
+        };
+        // the key that stopped recording
+        keys.pop();
+        self.macros.last = keys;
+        let Ok(Some(name)) = self.prompt("Macro recorded, save as (ESC to skip): ", |_, _, _| {})
+        else {
+            return;
+        };
+        let saved = macro_path(&name).and_then(|path| {
+            let lines: Vec<String> = self.macros.last.iter().map(Key::to_string).collect();
+            let text = lines.join("\n") + "\n";
+            fs::create_dir_all(path.parent()?).ok()?;
+            fs::write(path, text).ok()
+        });
+        self.status_message = StatusMessage::from(if saved.is_some() {
+            format!("Macro saved as {name}.")
+        } else {
+            format!("Could not save macro {name}.")
+        });
+    }
+
+    /// ask how often and which macro to play, then play it: a count, `*` for until a
+    /// command fails, and the name of a saved macro, by default the last one recorded
+    pub(super) fn play_macro(&mut self) -> Result<()> {
+        if self.macros.playing {
+            return Ok(());
+        }
+        if let Some(recording) = &mut self.macros.recording {
+            recording.pop();
+            self.status_message =
+                StatusMessage::from(String::from("Stop recording before playing a macro."));
+            return Ok(());
+        }
+        let prompt = "Play macro (times or * until it fails, and a saved name): ";
+        let options = self.prompt(prompt, |_, _, _| {})?.unwrap_or_default();
+        let (mut rounds, mut keys) = (1, self.macros.last.clone());
+        for option in options.split_whitespace() {
+            if option == "*" {
+                rounds = UNTIL_FAILURE_ROUNDS;
+            } else if let Ok(count) = option.parse() {
+                rounds = count;
+            } else {
+                match load_macro(option) {
+                    Ok(loaded) => keys = loaded,
+                    Err(err) => {
+                        self.status_message = StatusMessage::from(err);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        self.macros.playing = true;
+        'rounds: for _ in 0..rounds {
+            self.macros.playback.extend(keys.iter().copied());
+            while !self.macros.playback.is_empty() {
+                let played = self.process_keypress();
+                if played.is_err() || self.macros.failed {
+                    self.macros.playback.clear();
+                    self.macros.playing = false;
+                    played?;
+                    break 'rounds;
+                }
+            }
+        }
+        self.macros.playing = false;
+        Ok(())
+    }
+}
+
+fn macro_path(name: &str) -> Option<PathBuf> {
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+    Some(Config::directory()?.join("macros").join(name))
+}
+
+/// the keys of a saved macro, one per line
+fn load_macro(name: &str) -> std::result::Result<Vec<Key>, String> {
+    let text = macro_path(name)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .ok_or_else(|| format!("No macro named {name}."))?;
+    text.lines()
+        .map(|line| line.parse().map_err(|err| format!("Macro {name}: {err}.")))
+        .collect()
+}
@@ -1,11 +1,44 @@
+mod block_editor;
+mod bracket_editor;
+mod case_editor;
+mod clipboard_editor;
+mod comment_editor;
+mod completion_editor;
+mod cursors_editor;
 mod draw_editor;
+mod fill_editor;
+mod indent_editor;
+mod lines_editor;
+mod list_editor;
+mod macro_editor;
+mod number_editor;
+mod overwrite_editor;
+mod pairs_editor;
 mod process_editor;
+mod selection_editor;
+mod snippet_editor;
+mod transpose_editor;
+mod vim_editor;
+mod whitespace_editor;
+mod word_editor;
 
 use crate::Args;
+use crate::Clipboard;
+use crate::Command;
+use crate::Config;
 use crate::Document;
+use crate::Indent;
+use crate::Key;
+use crate::Keymap;
+use crate::Registers;
 use crate::Terminal;
 use clap::StructOpt;
+use completion_editor::Completion;
+use list_editor::Listing;
+use macro_editor::Macros;
+use std::cmp::Ordering;
 use std::time::Instant;
+use vim_editor::Vim;
 
 const QUIT_TIMES: u8 = 3;
 
@@ -15,12 +48,32 @@ pub enum SearchDirection {
     Backward,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Position {
     pub x: u16,
     pub y: u16,
 }
 
+impl Ord for Position {
+    /// positions are ordered as they appear in the document: by row, then column
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.y, self.x).cmp(&(other.y, other.x))
+    }
+}
+
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// a cursor besides the primary one, with the other end of its own selection
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    pub position: Position,
+    pub anchor: Option<Position>,
+}
+
 pub struct StatusMessage {
     pub text: String,
     pub time: Instant,
@@ -35,43 +88,152 @@ impl From<String> for StatusMessage {
     }
 }
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct Editor {
     should_quit: bool,
     terminal: Terminal,
     cursor_position: Position,
+    /// the other end of the selection, the cursor being the moving end
+    selection: Option<Position>,
+    /// extra cursors, all edited and moved together with the primary one
+    cursors: Vec<Cursor>,
+    /// the corner opposite the cursor of a rectangular selection
+    block: Option<Position>,
     document: Document,
     offset: Position,
     status_message: StatusMessage,
     quit_times: u8,
+    clipboard: Clipboard,
+    /// whether the last key typed a character, so a run of them is undone together
+    typing: bool,
+    /// settings as loaded, see `settings` for the ones that apply to the open file
+    config: Config,
+    /// `config` with the `[filetype]` section for the open file applied
+    settings: Config,
+    macros: Macros,
+    /// how many times to run the next command, while a repeat count is being typed
+    count: Option<usize>,
+    /// the vim keymap's state, when it is the one in use
+    vim: Option<Vim>,
+    keymap: Keymap,
+    /// the keys of a sequence typed so far, e.g. `Ctrl-x` waiting for `Ctrl-s`
+    pending_keys: Vec<Key>,
+    /// the command the last key sequence ran, if it was bound to one
+    last_command: Option<Command>,
+    /// whether moving the cursor extends the selection, as after setting the mark
+    mark: bool,
+    /// lines shown in place of the document, such as the key bindings
+    listing: Option<Listing>,
+    /// in overwrite mode, where each character typed over another one ended and the
+    /// grapheme it replaced; `None` in insert mode
+    overwritten: Option<Vec<(Position, String)>>,
+    /// the document's revision after the last character was typed over or brought back
+    overwritten_revision: usize,
+    /// the tab stops of the snippet being filled in still ahead, each with the start and
+    /// end of every copy of it
+    snippet_stops: Vec<Vec<(Position, Position)>>,
+    /// the completion popup, while it is open
+    completion: Option<Completion>,
+    /// where the word starts that an automatic popup last found nothing for, and the
+    /// document's revision then
+    completion_miss: Option<(Position, usize)>,
+    registers: Registers,
+    /// whether the last key typed text, which the `.` register gathers
+    typed_text: bool,
 }
 
 impl Editor {
     pub fn default() -> Self {
         let args = Args::parse();
-        let mut initial_status =
-            String::from("HELP: Ctrl-Q = quit | Ctrl-S = save | Ctrl-F = find");
-        let document = if let Some(filename) = args.file {
+        let (config, mut errors) = Config::load();
+        let (keymap, keymap_errors) = Keymap::load(&config.keymap);
+        errors.extend(keymap_errors);
+        let mut initial_status = if let Some(err) = errors.first() {
+            match errors.len() - 1 {
+                0 => format!("ERR: {err}"),
+                more => format!("ERR (+{more} more): {err}"),
+            }
+        } else {
+            let help: Vec<String> = [
+                Command::Quit,
+                Command::Save,
+                Command::Search,
+                Command::ListBindings,
+            ]
+            .into_iter()
+            .filter_map(|command| {
+                Some(format!(
+                    "{} = {}",
+                    keymap.keys_for(command)?,
+                    command.name()
+                ))
+            })
+            .collect();
+            format!("HELP: {}", help.join(" | "))
+        };
+        let mut document = if let Some(filename) = args.file {
             let doc = Document::open(&filename);
             if let Ok(doc) = doc {
                 doc
             } else {
-                initial_status = format!("ERR: Could not open file: {}", filename);
+                initial_status = format!("ERR: Could not open file: {filename}");
                 Document::default()
             }
         } else {
             Document::default()
         };
+        let settings = config.for_file_type(&document.file_type().name());
+        document.indent = Indent {
+            width: settings.indent_width,
+            hard_tabs: settings.hard_tabs,
+        };
+        if settings.detect_indent {
+            document.detect_indent();
+        }
+        let vim = (config.keymap == "vim").then(Vim::default);
 
         Self {
             should_quit: false,
             terminal: Terminal::default().expect("Failed to initialize terminal"),
             cursor_position: Position::default(),
+            selection: None,
+            cursors: Vec::new(),
+            block: None,
             document,
             offset: Position::default(),
             status_message: StatusMessage::from(initial_status),
             quit_times: QUIT_TIMES,
+            clipboard: Clipboard::new(&config),
+            typing: false,
+            config,
+            settings,
+            macros: Macros::default(),
+            count: None,
+            vim,
+            keymap,
+            pending_keys: Vec::new(),
+            last_command: None,
+            mark: false,
+            listing: None,
+            overwritten: None,
+            overwritten_revision: 0,
+            snippet_stops: Vec::new(),
+            completion: None,
+            completion_miss: None,
+            registers: Registers::load(),
+            typed_text: false,
         }
     }
+
+    /// the settings for the open file, its `[filetype]` section applied
+    fn settings(&self) -> &Config {
+        &self.settings
+    }
+
+    /// apply the `[filetype]` section for the open file, after its name changed
+    fn update_settings(&mut self) {
+        self.settings = self.config.for_file_type(&self.document.file_type().name());
+    }
     pub fn run(&mut self) {
         loop {
             if let Err(ref err) = self.refresh_screen() {
@@ -84,6 +246,9 @@ impl Editor {
                 die(err);
             }
         }
+        // the `.` and `/` registers are only written out here, and with the screen gone
+        // there is nowhere left to say that failed
+        let _ = self.registers.save();
     }
 }
 
@@ -0,0 +1,164 @@
+use super::Position;
+use super::StatusMessage;
+use crate::Editor;
+
+/// hex and binary numbers wrap around at this many bits, like unsigned integers
+const WRAP_BITS: u32 = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Radix {
+    Binary,
+    Decimal,
+    Hex,
+}
+
+/// a number found in a row, as grapheme indices `start..end`
+struct Number {
+    start: usize,
+    end: usize,
+    radix: Radix,
+}
+
+impl Editor {
+    /// add `delta` to the number at or after the cursor; with a rectangular selection,
+    /// to the first number in it on each line, `delta` more on every line down
+    pub(super) fn add_to_number(&mut self, delta: i128) {
+        let tab_width = self.document.indent.width;
+        let lines: Vec<(usize, usize)> = if let Some(block) = self.block_range() {
+            (block.top..=block.bottom)
+                .filter_map(|y| {
+                    let row = self.document.row(y)?;
+                    Some((y, row.index_at_column(block.left, tab_width)))
+                })
+                .collect()
+        } else {
+            let Position { x, y } = self.cursor_position;
+            vec![(y as usize, x as usize)]
+        };
+        let mut step = 0;
+        for (y, x) in lines {
+            let Some(row) = self.document.row(y) else {
+                continue;
+            };
+            let graphemes: Vec<&str> = row.graphemes().collect();
+            let Some(number) = find_number(&graphemes, x) else {
+                continue;
+            };
+            step += 1;
+            let replaced = bumped(
+                &graphemes[number.start..number.end].concat(),
+                number.radix,
+                delta * step,
+            );
+            let len = replaced.chars().count();
+            let text = [
+                graphemes[..number.start].concat(),
+                replaced,
+                graphemes[number.end..].concat(),
+            ]
+            .concat();
+            self.document.replace_lines(y..y + 1, &[text]);
+            if self.block.is_none() {
+                // on the last digit, so doing it again finds the same number
+                self.cursor_position.x = (number.start + len - 1) as u16;
+            }
+        }
+        if step == 0 {
+            self.status_message = StatusMessage::from(String::from("No number to change."));
+            self.macros.failed = true;
+        }
+        self.scroll();
+    }
+}
+
+/// the number `at` or after grapheme `at`: decimal with an optional `-`, or hex or
+/// binary with a `0x` or `0b` prefix
+fn find_number(graphemes: &[&str], at: usize) -> Option<Number> {
+    let is = |index: usize, test: fn(char) -> bool| {
+        graphemes.get(index).is_some_and(|grapheme| {
+            let mut chars = grapheme.chars();
+            chars.next().is_some_and(test) && chars.next().is_none()
+        })
+    };
+    let run = |from: usize, test: fn(char) -> bool| {
+        (from..graphemes.len())
+            .find(|&index| !is(index, test))
+            .unwrap_or(graphemes.len())
+    };
+    let prefixed = |index: usize, letter: &str, test: fn(char) -> bool| {
+        graphemes[index] == "0"
+            && graphemes
+                .get(index + 1)
+                .is_some_and(|grapheme| grapheme.eq_ignore_ascii_case(letter))
+            && is(index + 2, test)
+    };
+    let hex = |c: char| c.is_ascii_hexdigit();
+    let binary = |c: char| c == '0' || c == '1';
+    let mut index = 0;
+    while index < graphemes.len() {
+        let number = if prefixed(index, "x", hex) {
+            Some((run(index + 2, hex), Radix::Hex))
+        } else if prefixed(index, "b", binary) {
+            Some((run(index + 2, binary), Radix::Binary))
+        } else if is(index, |c| c.is_ascii_digit())
+            || (graphemes[index] == "-"
+                && is(index + 1, |c| c.is_ascii_digit())
+                // hex and binary numbers have no sign
+                && !prefixed(index + 1, "x", hex)
+                && !prefixed(index + 1, "b", binary))
+        {
+            Some((run(index + 1, |c| c.is_ascii_digit()), Radix::Decimal))
+        } else {
+            None
+        };
+        match number {
+            Some((end, radix)) if end > at => {
+                return Some(Number {
+                    start: index,
+                    end,
+                    radix,
+                })
+            }
+            Some((end, _)) => index = end,
+            None => index += 1,
+        }
+    }
+    None
+}
+
+/// `number` with `delta` added, as wide as it was, zero padding and the case of hex
+/// digits kept
+fn bumped(number: &str, radix: Radix, delta: i128) -> String {
+    let (sign, digits) = match radix {
+        Radix::Decimal => number
+            .strip_prefix('-')
+            .map_or(("", number), |digits| ("-", digits)),
+        Radix::Hex | Radix::Binary => (&number[..2], &number[2..]),
+    };
+    let width = if radix == Radix::Decimal && !digits.starts_with('0') {
+        0
+    } else {
+        digits.len()
+    };
+    let base = match radix {
+        Radix::Binary => 2,
+        Radix::Decimal => 10,
+        Radix::Hex => 16,
+    };
+    let Ok(value) = i128::from_str_radix(digits, base) else {
+        return number.to_string();
+    };
+    if radix == Radix::Decimal {
+        let value = if sign == "-" { -value } else { value }.saturating_add(delta);
+        let sign = if value < 0 { "-" } else { "" };
+        return format!("{sign}{:0width$}", value.unsigned_abs());
+    }
+    let value = value.saturating_add(delta).rem_euclid(1 << WRAP_BITS);
+    match radix {
+        Radix::Binary => format!("{sign}{value:0width$b}"),
+        _ if digits.chars().any(|c| c.is_ascii_uppercase()) => {
+            format!("{sign}{value:0width$X}")
+        }
+        _ => format!("{sign}{value:0width$x}"),
+    }
+}
@@ -0,0 +1,70 @@
+use super::Position;
+use super::StatusMessage;
+use crate::Editor;
+
+impl Editor {
+    /// switch between inserting typed characters and typing over the ones at the cursor
+    pub(super) fn toggle_overwrite(&mut self) {
+        let message = if self.overwritten.take().is_some() {
+            "Insert mode."
+        } else {
+            self.overwritten = Some(Vec::new());
+            "Overwrite mode."
+        };
+        self.status_message = StatusMessage::from(message.to_string());
+    }
+
+    pub(super) fn is_overwriting(&self) -> bool {
+        self.overwritten.is_some()
+    }
+
+    /// in overwrite mode, type `c` over the grapheme at the cursor, keeping what was
+    /// there for Backspace to bring back; `None` when `c` is to be inserted instead
+    pub(super) fn overwrite(&mut self, c: char) -> Option<(Position, Position)> {
+        if self.overwritten.is_none() || c == '\n' || c == '\t' {
+            return None;
+        }
+        self.forget_moved_overwrites();
+        let position = self.cursor_position.clone();
+        let replaced = self.document.replace_grapheme(&position, &c.to_string())?;
+        self.overwritten_revision = self.document.revision();
+        let end = Position {
+            x: position.x + 1,
+            y: position.y,
+        };
+        if let Some(overwritten) = &mut self.overwritten {
+            overwritten.push((end.clone(), replaced));
+        }
+        self.cursor_position = end.clone();
+        Some((end.clone(), end))
+    }
+
+    /// in overwrite mode, Backspace over a character typed this session puts back
+    /// the one it replaced
+    pub(super) fn restore_overwritten(&mut self) -> Option<(Position, Position)> {
+        self.forget_moved_overwrites();
+        let position = self.cursor_position.clone();
+        let overwritten = self.overwritten.as_mut()?;
+        let index = overwritten.iter().rposition(|(end, _)| *end == position)?;
+        let (_, replaced) = overwritten.remove(index);
+        let start = Position {
+            x: position.x - 1,
+            y: position.y,
+        };
+        self.document.replace_grapheme(&start, &replaced);
+        self.overwritten_revision = self.document.revision();
+        self.cursor_position = start;
+        Some((position.clone(), position))
+    }
+
+    /// drop the characters typed over when another edit came since, as it may have
+    /// moved them
+    fn forget_moved_overwrites(&mut self) {
+        if self.document.revision() == self.overwritten_revision {
+            return;
+        }
+        if let Some(overwritten) = &mut self.overwritten {
+            overwritten.clear();
+        }
+    }
+}
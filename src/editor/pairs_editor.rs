@@ -0,0 +1,74 @@
+use super::Position;
+use crate::Editor;
+
+impl Editor {
+    /// the characters typed in pairs for the open file, as `(open, close)`
+    fn auto_pairs(&self) -> Vec<(char, char)> {
+        let pairs = self
+            .settings()
+            .auto_pairs
+            .clone()
+            .unwrap_or_else(|| self.document.file_type().auto_pairs().to_string());
+        let chars: Vec<char> = pairs.chars().collect();
+        chars
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect()
+    }
+
+    /// the characters just before and at the cursor
+    fn around_cursor(&self) -> (Option<char>, Option<char>) {
+        let Position { x, y } = self.cursor_position;
+        let Some(row) = self.document.row(y as usize) else {
+            return (None, None);
+        };
+        let x = x as usize;
+        let previous = if x > 0 {
+            row.substring(x - 1, x).chars().next()
+        } else {
+            None
+        };
+        (previous, row.substring(x, x + 1).chars().next())
+    }
+
+    /// type `c` as part of a pair: step over a closer already under the cursor, or insert
+    /// an opener together with its closer; `None` when `c` is to be inserted as usual
+    pub(super) fn type_pair(&mut self, c: char) -> Option<(Position, Position)> {
+        let pairs = self.auto_pairs();
+        let position = self.cursor_position.clone();
+        let (previous, next) = self.around_cursor();
+        if next == Some(c) && pairs.iter().any(|&(_, close)| close == c) {
+            self.cursor_position.x += 1;
+            return Some((position.clone(), position));
+        }
+        let &(open, close) = pairs.iter().find(|&&(open, _)| open == c)?;
+        let word_chars = &self.settings().word_chars;
+        let is_word_char = |c: char| c.is_alphanumeric() || word_chars.contains(c);
+        if next.is_some_and(is_word_char) || (open == close && previous.is_some_and(is_word_char)) {
+            return None;
+        }
+        let end = self
+            .document
+            .insert_str(&position, &format!("{open}{close}"));
+        self.cursor_position = Position {
+            x: position.x + 1,
+            y: position.y,
+        };
+        Some((position, end))
+    }
+
+    /// Backspace between an empty pair deletes both characters
+    pub(super) fn delete_pair(&mut self) -> Option<(Position, Position)> {
+        let (Some(previous), Some(next)) = self.around_cursor() else {
+            return None;
+        };
+        if !self.auto_pairs().contains(&(previous, next)) {
+            return None;
+        }
+        let Position { x, y } = self.cursor_position;
+        let (start, end) = (Position { x: x - 1, y }, Position { x: x + 1, y });
+        self.document.delete_range(&start, &end);
+        self.cursor_position = start.clone();
+        Some((end, start))
+    }
+}
@@ -2,111 +2,399 @@ use super::Position;
 use super::SearchDirection;
 use super::StatusMessage;
 use super::QUIT_TIMES;
+use crate::keymap::sequence_text;
+use crate::Command;
 use crate::Editor;
+use crate::Key;
+use crate::Lookup;
+use crate::Modifiers;
+use crate::Nav;
 use crate::Result;
-use crate::Terminal;
-use termion::event::Key;
+use crate::Row;
+use std::mem;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// the most times a command can be repeated
+pub(super) const MAX_COUNT: usize = 10_000;
 
 impl Editor {
     pub fn process_keypress(&mut self) -> Result<()> {
-        let pressed_key = Terminal::read_key()?;
-        match pressed_key {
-            Key::Ctrl('q') => {
+        let pressed_key = self.read_key()?;
+        self.macros.failed = false;
+        if self.completion_key(pressed_key) {
+            return Ok(());
+        }
+        if self.vim_key(pressed_key)? {
+            return Ok(());
+        }
+        if self.pending_keys.is_empty() && self.read_count(pressed_key) {
+            return Ok(());
+        }
+        self.pending_keys.push(pressed_key);
+        let command = match self.keymap.lookup(&self.pending_keys) {
+            Lookup::Prefix => {
+                let typed = sequence_text(&self.pending_keys);
+                self.status_message = StatusMessage::from(format!("{typed}-"));
+                return Ok(());
+            }
+            Lookup::Unbound if self.pending_keys.len() > 1 => {
+                let typed = sequence_text(&mem::take(&mut self.pending_keys));
+                self.status_message = StatusMessage::from(format!("{typed} is not bound."));
+                return Ok(());
+            }
+            Lookup::Unbound => None,
+            Lookup::Command(Command::RepeatCount) => {
+                self.pending_keys.clear();
+                self.count = Some(0);
+                self.status_message = StatusMessage::from(String::from("Repeat: "));
+                return Ok(());
+            }
+            Lookup::Command(command) => Some(command),
+        };
+        self.pending_keys.clear();
+        let count = self.count.take().unwrap_or(1).max(1);
+        let typing = matches!(pressed_key, Key::Char(c) if !c.is_whitespace()) && count == 1;
+        // what is typed in vim's insert mode is undone with the command that began it
+        if !(self.vim_inserting() || typing && self.typing) {
+            self.document.begin_step(&self.cursor_position);
+        }
+        self.typing = typing;
+        if let (None, Key::Char(c)) = (command, pressed_key) {
+            // a run of typed text makes up the `.` register
+            let typed = c.to_string().repeat(count);
+            if self.typed_text {
+                self.registers.append('.', &typed);
+            } else {
+                self.registers.set('.', &typed);
+            }
+        }
+        self.typed_text = command.is_none() && matches!(pressed_key, Key::Char(_));
+        // only typing and Tab keep filling in a snippet
+        if command.is_some_and(|command| !matches!(command, Command::Indent | Command::Edit(_))) {
+            self.snippet_stops.clear();
+        }
+        // repeated, the command is still a single undo step
+        for _ in 0..count {
+            if let Some(command) = command {
+                self.run_command(command)?;
+            } else {
+                self.run_key(pressed_key);
+            }
+            self.last_command = command;
+            if self.should_quit || self.macros.failed {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// take `key` as part of a repeat count: Alt and a digit adds the digit, and plain
+    /// digits do once `repeat_count` started one; returns whether the key was used up
+    fn read_count(&mut self, key: Key) -> bool {
+        let digit = match key {
+            Key::Alt(c) => c.to_digit(10),
+            Key::Char(c) if self.count.is_some() => c.to_digit(10),
+            _ => None,
+        };
+        let Some(digit) = digit else {
+            return false;
+        };
+        let count = self
+            .count
+            .unwrap_or(0)
+            .saturating_mul(10)
+            .saturating_add(digit as usize)
+            .min(MAX_COUNT);
+        self.count = Some(count);
+        self.status_message = StatusMessage::from(format!("Repeat: {count}"));
+        true
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn run_command(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Quit => {
                 return {
                     self.quit();
                     Ok(())
                 }
             }
-            Key::Ctrl('s') => {
+            Command::Save => {
                 return {
                     self.save();
                     Ok(())
                 }
             }
-            Key::Ctrl('f') => self.search(),
+            Command::Search => self.search(SearchDirection::Forward),
+            Command::SearchBackward => self.search(SearchDirection::Backward),
+            Command::Undo => self.undo(false),
+            Command::Redo => self.undo(true),
+            Command::ConvertCase => self.convert_case(),
+            Command::Copy => {
+                self.copy(None);
+                if self.mark {
+                    self.mark = false;
+                    self.selection = None;
+                }
+            }
+            Command::Cut => self.cut(None),
+            Command::Paste => self.paste(None),
+            Command::CopyToRegister => self.copy_to_register(false)?,
+            Command::CutToRegister => self.copy_to_register(true)?,
+            Command::PasteFromRegister => self.paste_from_register()?,
+            Command::ListRegisters => self.list_registers()?,
+            Command::CursorAtNextMatch => self.add_cursor_at_next_match(),
+            Command::CursorsAtAllMatches => self.add_cursors_at_every_match(),
+            Command::JumpToBracket => self.jump_to_bracket(),
+            Command::MoveLinesUp => self.move_lines(true),
+            Command::MoveLinesDown => self.move_lines(false),
+            Command::DuplicateLines => self.duplicate_lines(),
+            Command::DeleteLines => self.delete_lines(),
+            Command::JoinLines => self.join_lines(),
+            Command::SortLines => self.sort_lines(),
+            Command::ToggleRecording => self.toggle_recording(),
+            Command::PlayMacro => self.play_macro()?,
+            Command::ToggleComment => self.toggle_comment(),
+            Command::CursorAbove => self.add_cursor_vertically(Key::Up),
+            Command::CursorBelow => self.add_cursor_vertically(Key::Down),
+            Command::Cancel => {
+                self.selection = None;
+                self.block = None;
+                self.mark = false;
+                self.cursors.clear();
+            }
+            Command::DocumentStart | Command::DocumentEnd => {
+                let y = if command == Command::DocumentStart {
+                    0
+                } else {
+                    self.document.len().saturating_sub(1)
+                };
+                let x = if command == Command::DocumentStart {
+                    0
+                } else {
+                    self.document.row(y).map_or(0, Row::len)
+                };
+                if self.mark {
+                    self.selection.get_or_insert(self.cursor_position.clone());
+                } else {
+                    self.selection = None;
+                }
+                self.cursors.clear();
+                self.cursor_position = Position {
+                    x: x as u16,
+                    y: y as u16,
+                };
+                self.scroll();
+            }
+            Command::Indent if !self.snippet_stops.is_empty() => self.next_tab_stop(),
+            Command::Indent if self.selection_range().is_some() => self.shift_lines(true),
+            Command::Indent => self.run_key(Key::Char('\t')),
+            Command::Dedent => self.shift_lines(false),
+            Command::KillLine => self.kill_line(),
+            Command::ToggleOverwrite => self.toggle_overwrite(),
+            Command::TransposeChars => self.transpose_chars(),
+            Command::TransposeWords => self.transpose_words(),
+            Command::TransposeLines => self.transpose_lines(),
+            Command::IncrementNumber => self.add_to_number(1),
+            Command::DecrementNumber => self.add_to_number(-1),
+            Command::FillParagraph => self.fill_paragraph(),
+            Command::CleanupWhitespace => self.cleanup_whitespace(),
+            Command::ExpandSnippet => self.expand_snippet(),
+            Command::Complete => self.complete(false),
+            Command::ListBindings => {
+                let bindings = self.keymap.describe();
+                self.show_list("Key bindings", bindings)?;
+            }
+            Command::Move(key) => self.navigate(key),
+            Command::Select(key) => self.select_move(key),
+            Command::Block(key) => self.block_move(key),
+            Command::Edit(key) => self.run_key(key),
+            Command::SetMark => {
+                self.selection = Some(self.cursor_position.clone());
+                self.mark = true;
+                self.status_message = StatusMessage::from(String::from("Mark set"));
+                return Ok(());
+            }
+            Command::RepeatCount => (),
+        }
+        self.reset_quit_times();
+        Ok(())
+    }
+
+    /// type a key no command is bound to, or delete the way an `Edit` command's key does
+    fn run_key(&mut self, pressed_key: Key) {
+        if matches!(
+            pressed_key,
+            Key::Char(_)
+                | Key::Delete
+                | Key::Backspace
+                | Key::Ctrl('h')
+                | Key::Mod(Modifiers::CTRL, Nav::Delete)
+        ) {
+            self.mark = false;
+            if self.block.is_some() {
+                self.edit_block(pressed_key);
+            } else {
+                self.edit_at_cursors(pressed_key);
+                if matches!(pressed_key, Key::Char(c) if c != '\n') && self.cursors.is_empty() {
+                    self.auto_fill();
+                    if self.settings().auto_complete || self.completion.is_some() {
+                        self.complete(true);
+                    }
+                }
+            }
+        }
+        self.reset_quit_times();
+    }
+
+    /// move the cursors, extending the selection from the mark while one is set
+    fn navigate(&mut self, key: Key) {
+        if self.mark {
+            self.select_move(key);
+        } else {
+            self.selection = None;
+            self.block = None;
+            self.move_cursors(key);
+        }
+    }
+
+    fn reset_quit_times(&mut self) {
+        if self.quit_times < QUIT_TIMES {
+            self.quit_times = QUIT_TIMES;
+            self.status_message = StatusMessage::from(String::new());
+        }
+    }
+
+    /// apply a typed character, Backspace or Delete at the cursor, returning where the
+    /// changed text ended before the edit and where it ends now
+    pub(super) fn edit(&mut self, key: Key) -> (Position, Position) {
+        let position = self.cursor_position.clone();
+        let edited = match key {
             Key::Char(c) => {
-                self.document.insert(&self.cursor_position, c);
-                self.move_cursor(Key::Right);
+                if let Some(edited) = self.overwrite(c) {
+                    edited
+                } else if let Some(edited) = self.type_pair(c) {
+                    edited
+                } else {
+                    self.cursor_position = self.document.insert(&position, c);
+                    (position, self.cursor_position.clone())
+                }
+            }
+            Key::Delete => {
+                let end = self.next_position(&position);
+                self.document.delete(&position);
+                (end, position)
+            }
+            // Ctrl-Backspace, which terminals send as Ctrl-H
+            Key::Ctrl('h') => {
+                self.cursor_position = self.word_moved(&position, false);
+                self.document.delete_range(&self.cursor_position, &position);
+                (position, self.cursor_position.clone())
+            }
+            Key::Mod(Modifiers::CTRL, Nav::Delete) => {
+                let end = self.word_moved(&position, true);
+                self.document.delete_range(&position, &end);
+                (end, position)
             }
-            Key::Delete => self.document.delete(&self.cursor_position),
             Key::Backspace => {
-                if self.cursor_position.x > 0 && self.cursor_position.y > 0 {
-                    self.move_cursor(Key::Left);
+                if let Some(edited) = self.restore_overwritten() {
+                    edited
+                } else if let Some(edited) = self.delete_pair() {
+                    edited
+                } else if let Some(new_position) = self.document.delete_indent_level(&position) {
+                    self.cursor_position = new_position;
+                    (position, self.cursor_position.clone())
+                } else if position.x > 0 {
+                    self.cursor_position = self.moved(&position, Key::Left);
                     self.document.delete(&self.cursor_position);
-                } else if self.cursor_position.x == 0 && self.cursor_position.y > 0 {
-                    let previous_row = self
-                        .document
-                        .row(self.cursor_position.y as usize - 1)
-                        .unwrap();
+                    (position, self.cursor_position.clone())
+                } else if position.y > 0 {
+                    let previous_row = self.document.row(position.y as usize - 1).unwrap();
                     let new_position = Position {
                         x: previous_row.len() as u16,
-                        y: self.cursor_position.y - 1,
+                        y: position.y - 1,
                     };
-                    self.document.delete(&self.cursor_position);
+                    self.document.delete_line(&position);
                     self.cursor_position = new_position;
+                    (position, self.cursor_position.clone())
+                } else {
+                    (position.clone(), position)
                 }
             }
-            Key::Up
-            | Key::Down
-            | Key::Left
-            | Key::Right
-            | Key::Home
-            | Key::End
-            | Key::PageDown
-            | Key::PageUp => self.move_cursor(pressed_key),
-            _ => (),
-        }
-        if self.quit_times < QUIT_TIMES {
-            self.quit_times = QUIT_TIMES;
-            self.status_message = StatusMessage::from(String::new());
-        }
-        Ok(())
+            _ => (position.clone(), position),
+        };
+        self.scroll();
+        edited
     }
 
-    fn search(&mut self) {
+    /// search as the query is typed, starting in `direaction` from the cursor
+    pub(super) fn search(&mut self, mut direaction: SearchDirection) {
         let old_position = self.cursor_position.clone();
-        let mut direaction = SearchDirection::Forward;
+        let mut all_matches = false;
+        let mut found = false;
         if let Some(query) = self
             .prompt(
-                "Search (ESC to cancel, Arrows to navigate): ",
-                |editor, key, query| {
+                "Search (ESC to cancel, Arrows to navigate, Alt-Enter to add a cursor at every match): ",
+                |editor, command, query| {
                     let mut moved = false;
-                    match key {
-                        Key::Right | Key::Down => {
+                    match command {
+                        Some(Command::CursorsAtAllMatches) => all_matches = true,
+                        Some(Command::Move(Key::Right | Key::Down) | Command::Search) => {
                             editor.move_cursor(Key::Right);
                             direaction = SearchDirection::Forward;
                             moved = true;
                         }
-                        Key::Left | Key::Up => {
+                        Some(Command::Move(Key::Left | Key::Up) | Command::SearchBackward) => {
                             direaction = SearchDirection::Backward;
                             moved = true
                         }
                         _ => (),
                     }
-                    if let Some(position) = editor.document.find(query, &editor.cursor_position) {
+                    let mut from = editor.cursor_position.clone();
+                    if !moved && direaction == SearchDirection::Backward {
+                        // searching backward, the match at the cursor stays while it fits
+                        let len = editor.document.row(from.y as usize).map_or(0, Row::len);
+                        from.x = (from.x as usize + query.graphemes(true).count()).min(len) as u16;
+                    }
+                    if let Some(position) = editor.document.find(query, &from, direaction) {
                         editor.cursor_position = position;
                         editor.scroll();
+                        found = true;
                     } else if moved {
                         editor.move_cursor(Key::Left);
+                    } else {
+                        found = false;
                     }
                 },
             )
             .unwrap_or(None)
         {
-            if let Some(find_position) = self.document.find(query.as_str(), &old_position) {
-                self.cursor_position = find_position;
-                self.status_message = StatusMessage::from(String::new())
+            self.registers.set('/', &query);
+            if all_matches {
+                self.add_cursors_at_matches(&query);
+                return;
+            }
+            if found {
+                // the cursor stays on the match the search last went to
+                self.status_message = StatusMessage::from(String::new());
             } else {
                 self.cursor_position = old_position;
                 self.status_message = StatusMessage::from(format!("Not found :{}.", query));
+                self.macros.failed = true;
             }
         } else {
             self.status_message = StatusMessage::from(String::new());
         }
     }
 
-    fn move_cursor(&mut self, key: Key) {
-        let Position { mut x, mut y } = self.cursor_position;
+    pub(super) fn move_cursor(&mut self, key: Key) {
+        self.cursor_position = self.moved(&self.cursor_position, key);
+        self.scroll();
+    }
+
+    /// where `key` would take a cursor standing at `position`
+    pub(super) fn moved(&self, position: &Position, key: Key) -> Position {
+        let Position { mut x, mut y } = *position;
         let height = self.document.len() as u16;
         let width = if let Some(row) = self.document.row(y.into()) {
             row.len() as u16
@@ -145,6 +433,8 @@ impl Editor {
             }
             Key::Home => x = 0,
             Key::End => x = width,
+            Key::Mod(Modifiers::CTRL, Nav::Left) => return self.word_moved(position, false),
+            Key::Mod(Modifiers::CTRL, Nav::Right) => return self.word_moved(position, true),
             Key::PageUp => {
                 y = if y > terminal_height {
                     y - terminal_height
@@ -161,12 +451,24 @@ impl Editor {
             }
             _ => (),
         };
-        self.cursor_position = Position { x, y };
-        self.scroll();
+        if y != position.y && matches!(key, Key::Up | Key::Down | Key::PageUp | Key::PageDown) {
+            // keep the display column, so tabs and wide characters don't make the cursor drift
+            let column = self.display_column(position);
+            let tab_width = self.document.indent.width;
+            if let Some(row) = self.document.row(y.into()) {
+                x = if column <= row.width(tab_width) {
+                    row.index_at_column(column, tab_width)
+                } else {
+                    row.len() + column - row.width(tab_width)
+                } as u16;
+            }
+        }
+        Position { x, y }
     }
 
-    fn scroll(&mut self) {
-        let Position { x, y } = self.cursor_position;
+    pub(super) fn scroll(&mut self) {
+        let x = self.display_column(&self.cursor_position) as u16;
+        let y = self.cursor_position.y;
         let (width, height) = (self.terminal.size().width, self.terminal.size().height);
         let offset = &mut self.offset;
 
@@ -183,34 +485,39 @@ impl Editor {
         }
     }
 
-    fn prompt<F>(&mut self, prompt: &str, callback: F) -> Result<Option<String>>
+    /// read a line in the message bar, calling `callback` with the command bound to each
+    /// key pressed; `cancel` gives up, `backspace` deletes and Enter accepts the line
+    pub(super) fn prompt<F>(&mut self, prompt: &str, mut callback: F) -> Result<Option<String>>
     where
-        F: Fn(&mut Self, Key, &str),
+        F: FnMut(&mut Self, Option<Command>, &str),
     {
         let mut result = String::new();
         loop {
             self.status_message = StatusMessage::from(format!("{}{}", prompt, result));
             self.refresh_screen()?;
-            let key = Terminal::read_key()?;
-            match key {
-                Key::Char('\n') => break,
-                Key::Char(c) => {
-                    if !c.is_control() {
-                        result.push(c);
-                    }
-                }
-                Key::Backspace => {
-                    if !result.is_empty() {
-                        result.truncate(result.len() - 1);
-                    }
-                }
-                Key::Esc => {
+            let key = self.read_key()?;
+            let command = match self.keymap.lookup(&[key]) {
+                Lookup::Command(command) => Some(command),
+                _ => None,
+            };
+            match (key, command) {
+                (_, Some(Command::Cancel)) => {
                     result.truncate(0);
                     break;
                 }
+                (Key::Char('\n'), _) | (_, Some(Command::CursorsAtAllMatches)) => {
+                    callback(self, command, &result);
+                    break;
+                }
+                (Key::Char(c), _) if !c.is_control() => {
+                    result.push(c);
+                }
+                (_, Some(Command::Edit(Key::Backspace))) => {
+                    result.pop();
+                }
                 _ => (),
             }
-            callback(self, key, &result);
+            callback(self, command, &result);
         }
         if result.is_empty() {
             Ok(None)
@@ -219,28 +526,58 @@ impl Editor {
         }
     }
 
-    fn save(&mut self) {
+    pub(super) fn save(&mut self) {
         if self.document.filename.is_none() {
             let new_filename = self.prompt("Save as: ", |_, _, _| {}).unwrap_or(None);
             match new_filename {
-                Some(filename) => self.document.filename = Some(filename),
+                Some(filename) => {
+                    self.document.filename = Some(filename);
+                    self.update_settings();
+                }
                 None => {
                     self.status_message = StatusMessage::from("Save aborted.".to_string());
                     return;
                 }
             }
         }
-        if self.document.save().is_ok() {
+        if self.save_document().is_ok() {
             self.status_message = StatusMessage::from("File saved successfully.".to_string());
         } else {
             self.status_message = StatusMessage::from("Error writing file!".to_string());
         }
     }
 
-    fn quit(&mut self) {
+    /// undo the last step, or redo the last undone one
+    pub(super) fn undo(&mut self, redo: bool) {
+        let restored = if redo {
+            self.document.redo(&self.cursor_position)
+        } else {
+            self.document.undo(&self.cursor_position)
+        };
+        let Some(position) = restored else {
+            let message = if redo {
+                "Nothing to redo."
+            } else {
+                "Nothing to undo."
+            };
+            self.status_message = StatusMessage::from(message.to_string());
+            return;
+        };
+        self.cursor_position = position;
+        self.selection = None;
+        self.block = None;
+        self.cursors.clear();
+        self.scroll();
+    }
+
+    pub(super) fn quit(&mut self) {
         if self.document.is_dirty() && self.quit_times > 0 {
+            let keys = self
+                .keymap
+                .keys_for(Command::Quit)
+                .unwrap_or_else(|| String::from("quit"));
             self.status_message = StatusMessage::from(format!(
-                "WARNING! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
+                "WARNING! File has unsaved changes. Press {keys} {} more times to quit.",
                 self.quit_times
             ));
             self.quit_times -= 1;
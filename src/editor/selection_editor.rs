@@ -0,0 +1,116 @@
+use super::Position;
+use crate::Editor;
+use crate::Key;
+use std::cmp::Ordering;
+
+impl Editor {
+    /// the selected range, ordered start to end, if anything is selected
+    pub(super) fn selection_range(&self) -> Option<(Position, Position)> {
+        ordered(self.selection.as_ref()?, &self.cursor_position)
+    }
+
+    /// the highlighted columns of row `y`, given the row's length: every selection,
+    /// plus the cell under each extra cursor since the terminal only draws one
+    pub(super) fn selected_columns(&self, y: usize, len: usize) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let selections = self
+            .cursors
+            .iter()
+            .filter_map(|cursor| ordered(cursor.anchor.as_ref()?, &cursor.position))
+            .chain(self.selection_range());
+        for (start, end) in selections {
+            if y < start.y as usize || y > end.y as usize {
+                continue;
+            }
+            let from = if y == start.y as usize {
+                start.x as usize
+            } else {
+                0
+            };
+            let to = if y == end.y as usize {
+                end.x as usize
+            } else {
+                len
+            };
+            ranges.push((from, to.min(len)));
+        }
+        if let Some(block) = self.block_range() {
+            if let Some(row) = self
+                .document
+                .row(y)
+                .filter(|_| (block.top..=block.bottom).contains(&y))
+            {
+                ranges.push(self.block_columns(&block, row));
+            }
+        }
+        for cursor in &self.cursors {
+            if cursor.position.y as usize == y {
+                let x = cursor.position.x as usize;
+                ranges.push((x, x + 1));
+            }
+        }
+        ranges.sort_unstable();
+        ranges
+    }
+
+    /// move every cursor while keeping the other ends of their selections in place
+    pub(super) fn select_move(&mut self, key: Key) {
+        self.block = None;
+        if self.selection.is_none() {
+            self.selection = Some(self.cursor_position.clone());
+        }
+        for index in 0..self.cursors.len() {
+            let cursor = &self.cursors[index];
+            let moved = self.moved(&cursor.position, key);
+            let cursor = &mut self.cursors[index];
+            if cursor.anchor.is_none() {
+                cursor.anchor = Some(cursor.position.clone());
+            }
+            cursor.position = moved;
+        }
+        self.move_cursor(key);
+    }
+
+    /// delete the selected text, returning whether there was any
+    pub(super) fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.selection_range() else {
+            self.selection = None;
+            return false;
+        };
+        self.document.delete_range(&start, &end);
+        for cursor in &mut self.cursors {
+            cursor.position = shifted(&cursor.position, &end, &start);
+        }
+        self.cursor_position = start;
+        self.selection = None;
+        self.scroll();
+        true
+    }
+}
+
+/// the two ends of a selection in document order, `None` when it is empty
+pub(super) fn ordered(anchor: &Position, cursor: &Position) -> Option<(Position, Position)> {
+    match anchor.cmp(cursor) {
+        Ordering::Less => Some((anchor.clone(), cursor.clone())),
+        Ordering::Greater => Some((cursor.clone(), anchor.clone())),
+        Ordering::Equal => None,
+    }
+}
+
+/// where `position` ends up after the text before `end` was edited so that it now ends at `new_end`
+pub(super) fn shifted(position: &Position, end: &Position, new_end: &Position) -> Position {
+    if position < end {
+        return position.clone().min(new_end.clone());
+    }
+    if position.y == end.y {
+        Position {
+            x: new_end.x + (position.x - end.x),
+            y: new_end.y,
+        }
+    } else {
+        Position {
+            x: position.x,
+            y: position.y - end.y + new_end.y,
+        }
+    }
+}
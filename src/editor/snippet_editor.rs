@@ -0,0 +1,105 @@
+use super::selection_editor::shifted;
+use super::Cursor;
+use super::Position;
+use super::StatusMessage;
+use crate::Editor;
+use crate::Snippet;
+
+impl Editor {
+    /// replace the snippet name before the cursor with the snippet, selecting its first
+    /// tab stop
+    pub(super) fn expand_snippet(&mut self) {
+        if let Err(message) = self.try_expand_snippet() {
+            self.status_message = StatusMessage::from(message);
+            self.macros.failed = true;
+        }
+    }
+
+    fn try_expand_snippet(&mut self) -> Result<(), String> {
+        if !self.cursors.is_empty() || self.block.is_some() {
+            return Err("Snippets expand at a single cursor.".to_string());
+        }
+        let Position { x, y } = self.cursor_position;
+        let row = self
+            .document
+            .row(y as usize)
+            .ok_or_else(|| "No snippet name before the cursor.".to_string())?;
+        let (start, end) = row.word_at(x as usize, &self.settings().word_chars);
+        if start == end || end != x as usize {
+            return Err("No snippet name before the cursor.".to_string());
+        }
+        let name = row.substring(start, end);
+        let indentation = row.indentation().to_string();
+        let snippet: Snippet = self
+            .settings()
+            .snippets
+            .iter()
+            .rev()
+            .find(|(named, _)| *named == name)
+            .and_then(|(_, body)| body.parse().ok())
+            .ok_or_else(|| format!("No snippet named `{name}`."))?;
+        let snippet = snippet.indented(&indentation, &self.document.indent.unit());
+
+        let at = Position { x: start as u16, y };
+        self.document.delete_range(&at, &self.cursor_position);
+        let after = self.document.insert_str(&at, &snippet.lines.join("\n"));
+        let place = |line: usize, column: usize| Position {
+            x: (if line == 0 { start + column } else { column }) as u16,
+            y: y + line as u16,
+        };
+        let mut numbers: Vec<usize> = snippet.fields.iter().map(|field| field.stop).collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+        // `$0` comes last, and the end of the snippet stands in for it when left out
+        if numbers.first() == Some(&0) {
+            numbers.rotate_left(1);
+        }
+        self.snippet_stops = numbers
+            .into_iter()
+            .map(|number| {
+                snippet
+                    .fields
+                    .iter()
+                    .filter(|field| field.stop == number)
+                    .map(|field| (place(field.line, field.start), place(field.line, field.end)))
+                    .collect()
+            })
+            .collect();
+        if !snippet.fields.iter().any(|field| field.stop == 0) {
+            self.snippet_stops.push(vec![(after.clone(), after)]);
+        }
+        self.selection = None;
+        self.next_tab_stop();
+        Ok(())
+    }
+
+    /// select every copy of the next tab stop of the snippet being filled in, so typing
+    /// replaces them all together
+    pub(super) fn next_tab_stop(&mut self) {
+        if self.snippet_stops.is_empty() {
+            return;
+        }
+        let mut copies = self.snippet_stops.remove(0).into_iter();
+        let Some((start, end)) = copies.next() else {
+            return;
+        };
+        self.selection = (start != end).then_some(start);
+        self.cursor_position = end;
+        self.cursors = copies
+            .map(|(start, end)| Cursor {
+                anchor: (start != end).then_some(start),
+                position: end,
+            })
+            .collect();
+        self.scroll();
+    }
+
+    /// move the tab stops still ahead along with an edit of the text before `end`
+    /// that left it ending at `new_end`
+    pub(super) fn shift_tab_stops(&mut self, end: &Position, new_end: &Position) {
+        for (start, stop_end) in self.snippet_stops.iter_mut().flatten() {
+            *start = shifted(start, end, new_end);
+            *stop_end = shifted(stop_end, end, new_end);
+        }
+    }
+}
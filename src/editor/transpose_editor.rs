@@ -0,0 +1,81 @@
+use super::Position;
+use super::StatusMessage;
+use crate::Editor;
+
+impl Editor {
+    /// swap the grapheme before the cursor with the one at it and step past both; at
+    /// the end of a row, swap the two before the cursor
+    pub(super) fn transpose_chars(&mut self) {
+        let Position { x, y } = self.cursor_position;
+        let Some(row) = self.document.row(y as usize) else {
+            return self.nothing_to_transpose();
+        };
+        let graphemes: Vec<&str> = row.graphemes().collect();
+        let at = (x as usize).min(graphemes.len().saturating_sub(1));
+        if at == 0 {
+            return self.nothing_to_transpose();
+        }
+        let mut swapped = graphemes.clone();
+        swapped.swap(at - 1, at);
+        self.document
+            .replace_lines(y as usize..y as usize + 1, &[swapped.concat()]);
+        self.cursor_position = Position {
+            x: at as u16 + 1,
+            y,
+        };
+        self.scroll();
+    }
+
+    /// swap the word before the cursor with the one after it, the cursor ending up
+    /// after both; within a word, that word is the first of the two
+    pub(super) fn transpose_words(&mut self) {
+        let Position { x, y } = self.cursor_position;
+        let Some(row) = self.document.row(y as usize) else {
+            return self.nothing_to_transpose();
+        };
+        let words = row.words(&self.settings().word_chars);
+        if words.len() < 2 {
+            return self.nothing_to_transpose();
+        }
+        let second = words
+            .iter()
+            .position(|&(start, _)| start >= x as usize)
+            .unwrap_or(words.len() - 1)
+            .max(1);
+        let ((first_start, first_end), (second_start, second_end)) =
+            (words[second - 1], words[second]);
+        let swapped = [
+            row.substring(0, first_start),
+            row.substring(second_start, second_end),
+            row.substring(first_end, second_start),
+            row.substring(first_start, first_end),
+            row.substring(second_end, row.len()),
+        ]
+        .concat();
+        self.document
+            .replace_lines(y as usize..y as usize + 1, &[swapped]);
+        self.cursor_position = Position {
+            x: second_end as u16,
+            y,
+        };
+        self.scroll();
+    }
+
+    /// swap the current line with the one above it and go on to the next line
+    pub(super) fn transpose_lines(&mut self) {
+        let y = self.cursor_position.y as usize;
+        if y == 0 || !self.document.move_lines(y..y + 1, true) {
+            return self.nothing_to_transpose();
+        }
+        self.cursor_position = Position {
+            x: 0,
+            y: (y + 1).min(self.document.len() - 1) as u16,
+        };
+        self.scroll();
+    }
+
+    fn nothing_to_transpose(&mut self) {
+        self.status_message = StatusMessage::from(String::from("Nothing to transpose."));
+        self.macros.failed = true;
+    }
+}
@@ -0,0 +1,762 @@
+use std::mem;
+
+use super::process_editor::MAX_COUNT;
+use super::Position;
+use super::SearchDirection;
+use super::StatusMessage;
+use crate::Editor;
+use crate::Key;
+use crate::Registers;
+use crate::Result;
+use crate::Row;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// motions, which move the cursor or tell an operator how far to reach
+const MOTIONS: [&str; 11] = ["h", "j", "k", "l", "w", "b", "e", "0", "$", "gg", "G"];
+/// normal mode commands that take no motion
+const ACTIONS: [&str; 18] = [
+    "x", "X", "D", "C", "p", "P", "u", "i", "a", "I", "A", "o", "O", "v", "J", ":", ".", "/",
+];
+/// the actions `.` repeats, besides the `d` and `c` operators
+const CHANGES: [&str; 13] = [
+    "x", "X", "D", "C", "p", "P", "i", "a", "I", "A", "o", "O", "J",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    Command,
+}
+
+/// the state of the vim keymap
+#[derive(Default)]
+pub(super) struct Vim {
+    mode: Mode,
+    /// the normal mode command typed so far
+    pending: String,
+    /// the keys of the command typed so far
+    keys: Vec<Key>,
+    /// the keys of a change that went on into insert mode
+    change: Option<Vec<Key>>,
+    /// the keys of the last change, which `.` plays again
+    last_change: Vec<Key>,
+    /// the register named by `"` for the command being typed
+    register: Option<char>,
+}
+
+/// a complete normal mode command
+struct Command {
+    count: Option<usize>,
+    operator: Option<char>,
+    /// the motion or action, or the operator again for a whole-line `dd`, `cc` or `yy`
+    name: String,
+}
+
+enum Parsed {
+    Incomplete,
+    Invalid,
+    Complete(Command),
+}
+
+/// how much of the text between the cursor and the target of a motion it covers
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Reach {
+    Exclusive,
+    Inclusive,
+    Lines,
+}
+
+impl Editor {
+    /// the mode to show in the status bar when the vim keymap is on
+    pub(super) fn vim_mode_name(&self) -> Option<&'static str> {
+        Some(match self.vim.as_ref()?.mode {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+            Mode::Command => "COMMAND",
+        })
+    }
+
+    pub(super) fn vim_inserting(&self) -> bool {
+        self.vim
+            .as_ref()
+            .is_some_and(|vim| vim.mode == Mode::Insert)
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        if let Some(vim) = &mut self.vim {
+            vim.mode = mode;
+        }
+    }
+
+    /// handle `key` the vim way, returning `false` for keys left to the usual bindings:
+    /// anything typed in insert mode and keys vim has no use for
+    pub(super) fn vim_key(&mut self, key: Key) -> Result<bool> {
+        let Some(vim) = &mut self.vim else {
+            return Ok(false);
+        };
+        if vim.mode == Mode::Insert {
+            if let Some(change) = &mut vim.change {
+                change.push(key);
+            }
+            if key != Key::Esc {
+                return Ok(false);
+            }
+            vim.mode = Mode::Normal;
+            if let Some(change) = vim.change.take() {
+                vim.last_change = change;
+            }
+            self.cursor_position.x = self.cursor_position.x.saturating_sub(1);
+            self.keep_on_text();
+            return Ok(true);
+        }
+        let c = match key {
+            Key::Char('\n') | Key::Down => 'j',
+            Key::Char(c) => c,
+            Key::Left | Key::Backspace => 'h',
+            Key::Right => 'l',
+            Key::Up => 'k',
+            Key::Delete => 'x',
+            Key::Esc => {
+                vim.pending.clear();
+                vim.keys.clear();
+                vim.register = None;
+                vim.mode = Mode::Normal;
+                self.selection = None;
+                self.keep_on_text();
+                return Ok(true);
+            }
+            Key::Ctrl('r') => {
+                self.undo(true);
+                return Ok(true);
+            }
+            Key::Ctrl(c @ ('a' | 'x')) if vim.mode == Mode::Normal => {
+                let count = split_count(&vim.pending).0.unwrap_or(1);
+                let count = i128::try_from(count).unwrap_or(i128::MAX);
+                vim.keys.push(key);
+                vim.last_change = mem::take(&mut vim.keys);
+                vim.pending.clear();
+                self.document.begin_step(&self.cursor_position);
+                self.add_to_number(if c == 'a' { count } else { -count });
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        };
+        self.document.begin_step(&self.cursor_position);
+        if vim.mode == Mode::Visual && vim.pending.is_empty() && "dxcy".contains(c) {
+            self.visual_operate(if c == 'x' { 'd' } else { c });
+            if let Some(vim) = &mut self.vim {
+                vim.register = None;
+            }
+            self.keep_on_text();
+            return Ok(true);
+        }
+        vim.keys.push(key);
+        if split_count(&vim.pending).1 == "\"" {
+            // `"a` names the register the next command yanks into or puts from, keeping
+            // a count typed before it
+            vim.pending.pop();
+            vim.register = Some(c);
+            return Ok(true);
+        }
+        vim.pending.push(c);
+        if split_count(&vim.pending).1 == "\"" {
+            return Ok(true);
+        }
+        let command = match parse(&vim.pending) {
+            Parsed::Incomplete => return Ok(true),
+            Parsed::Invalid => {
+                vim.pending.clear();
+                vim.keys.clear();
+                return Ok(true);
+            }
+            Parsed::Complete(command) => command,
+        };
+        vim.pending.clear();
+        let keys = mem::take(&mut vim.keys);
+        let visual = vim.mode == Mode::Visual;
+        let register = vim.register.take();
+        let changed = if visual {
+            self.visual_command(&command)?;
+            false
+        } else {
+            self.normal_command(&command, register)?
+        };
+        if let Some(vim) = self.vim.as_mut().filter(|_| changed) {
+            if vim.mode == Mode::Insert {
+                vim.change = Some(keys);
+            } else {
+                vim.last_change = keys;
+            }
+        }
+        self.keep_on_text();
+        self.scroll();
+        Ok(true)
+    }
+
+    /// run a normal mode command, returning whether it is a change `.` repeats
+    fn normal_command(&mut self, command: &Command, register: Option<char>) -> Result<bool> {
+        let count = command.count.unwrap_or(1);
+        let name = command.name.as_str();
+        let from = self.cursor_position.clone();
+        let Position { x, y } = from;
+        let len = self.row_len(y);
+        if let Some(operator) = command.operator {
+            if let Some((target, reach)) = self.operator_target(operator, command) {
+                self.operate(operator, &from, &target, reach, register);
+            }
+            return Ok(operator != 'y');
+        }
+        match name {
+            "x" | "X" | "D" | "C" => {
+                let (operator, motion) = match name {
+                    "x" => ('d', "l"),
+                    "X" => ('d', "h"),
+                    "D" => ('d', "$"),
+                    _ => ('c', "$"),
+                };
+                if let Some((target, reach)) = self.motion(motion, command.count, true) {
+                    self.operate(operator, &from, &target, reach, register);
+                }
+            }
+            "p" | "P" => self.vim_paste(name == "p", count, register),
+            "u" => self.undo(false),
+            "i" | "a" | "I" | "A" => {
+                self.cursor_position.x = match name {
+                    "a" => (x + 1).min(len),
+                    "I" => self.first_non_blank(y),
+                    "A" => len,
+                    _ => x,
+                };
+                self.set_mode(Mode::Insert);
+            }
+            "o" => {
+                self.cursor_position = self.document.insert_newline(&Position { x: len, y });
+                self.set_mode(Mode::Insert);
+            }
+            "O" => {
+                let indentation = self
+                    .document
+                    .row(y as usize)
+                    .map_or(String::new(), |row| row.indentation().to_string());
+                self.document
+                    .insert_str(&Position { x: 0, y }, &format!("{indentation}\n"));
+                self.cursor_position = Position {
+                    x: indentation.graphemes(true).count() as u16,
+                    y,
+                };
+                self.set_mode(Mode::Insert);
+            }
+            "v" => {
+                self.selection = Some(from);
+                self.set_mode(Mode::Visual);
+            }
+            "J" => {
+                for _ in 0..count.max(2) - 1 {
+                    self.join_lines();
+                }
+            }
+            ":" => self.vim_command_line()?,
+            "." => {
+                let keys = self
+                    .vim
+                    .as_ref()
+                    .map_or(Vec::new(), |vim| vim.last_change.clone());
+                for _ in 0..count {
+                    self.queue_keys(&keys);
+                }
+            }
+            "/" => self.search(SearchDirection::Forward),
+            motion => {
+                if let Some((target, _)) = self.motion(motion, command.count, false) {
+                    self.cursor_position = target;
+                }
+            }
+        }
+        Ok(CHANGES.contains(&name))
+    }
+
+    /// how far an operator reaches: whole lines for `dd`, `cc` and `yy`, else its motion's way
+    fn operator_target(&self, operator: char, command: &Command) -> Option<(Position, Reach)> {
+        let name = command.name.as_str();
+        let from = &self.cursor_position;
+        let y = from.y;
+        if name.starts_with(operator) {
+            let last = (y as usize)
+                .saturating_add(command.count.unwrap_or(1) - 1)
+                .min(self.last_line());
+            return Some((
+                Position {
+                    x: 0,
+                    y: last as u16,
+                },
+                Reach::Lines,
+            ));
+        }
+        if operator == 'c' && name == "w" && char_class(self.char_at(from)) != 0 {
+            // `cw` changes to the end of the word it starts in, like `ce`
+            let end = (1..command.count.unwrap_or(1)).fold(self.word_end(from), |position, _| {
+                self.word_end_after(&position)
+            });
+            return Some((end, Reach::Inclusive));
+        }
+        let (target, reach) = self.motion(name, command.count, true)?;
+        if name == "w" && target.y > y {
+            // `dw` on the last word of a row stops at the end of the row
+            return Some((
+                Position {
+                    x: self.row_len(y),
+                    y,
+                },
+                reach,
+            ));
+        }
+        Some((target, reach))
+    }
+
+    /// in visual mode, motions move the cursor and leave the other end of the selection
+    fn visual_command(&mut self, command: &Command) -> Result<()> {
+        match command.name.as_str() {
+            "v" => {
+                self.selection = None;
+                self.set_mode(Mode::Normal);
+            }
+            ":" => self.vim_command_line()?,
+            name if command.operator.is_none() && MOTIONS.contains(&name) => {
+                if let Some((target, _)) = self.motion(name, command.count, false) {
+                    self.cursor_position = target;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// apply `operator` to the selection, which takes in the character under the cursor
+    fn visual_operate(&mut self, operator: char) {
+        let anchor = self
+            .selection
+            .take()
+            .unwrap_or(self.cursor_position.clone());
+        let cursor = self.cursor_position.clone();
+        let register = self.vim.as_mut().and_then(|vim| vim.register.take());
+        self.set_mode(Mode::Normal);
+        self.operate(operator, &anchor, &cursor, Reach::Inclusive, register);
+    }
+
+    /// where a motion takes the cursor, and how much of the way an operator covers;
+    /// `$` and `l` may go past the last character when they are for an operator
+    fn motion(
+        &self,
+        name: &str,
+        count: Option<usize>,
+        for_operator: bool,
+    ) -> Option<(Position, Reach)> {
+        let times = count.unwrap_or(1);
+        let Position { x, y } = self.cursor_position;
+        let last_x = if for_operator {
+            self.row_len(y)
+        } else {
+            self.row_len(y).saturating_sub(1)
+        };
+        let line = |y: usize| {
+            let y = y.min(self.last_line()) as u16;
+            (Position { x, y }, Reach::Lines)
+        };
+        let repeated = |step: fn(&Self, &Position) -> Position| {
+            (0..times).fold(self.cursor_position.clone(), |position, _| {
+                step(self, &position)
+            })
+        };
+        Some(match name {
+            "h" => (
+                Position {
+                    x: x.saturating_sub(times as u16),
+                    y,
+                },
+                Reach::Exclusive,
+            ),
+            "l" => (
+                Position {
+                    x: (x as usize).saturating_add(times).min(last_x as usize) as u16,
+                    y,
+                },
+                Reach::Exclusive,
+            ),
+            "j" => line((y as usize).saturating_add(times)),
+            "k" => line((y as usize).saturating_sub(times)),
+            "w" => (repeated(Self::word_start_after), Reach::Exclusive),
+            "b" => (repeated(Self::word_start_before), Reach::Exclusive),
+            "e" => (repeated(Self::word_end_after), Reach::Inclusive),
+            "0" => (Position { x: 0, y }, Reach::Exclusive),
+            "$" => (
+                Position {
+                    x: self.row_len(y).saturating_sub(1),
+                    y,
+                },
+                Reach::Inclusive,
+            ),
+            "gg" | "G" => {
+                let target = match (name, count) {
+                    (_, Some(line)) => line.saturating_sub(1),
+                    ("gg", None) => 0,
+                    _ => self.last_line(),
+                }
+                .min(self.last_line());
+                let target = Position {
+                    x: self.first_non_blank(target as u16),
+                    y: target as u16,
+                };
+                (target, Reach::Lines)
+            }
+            _ => return None,
+        })
+    }
+
+    /// delete, change or yank from `from` to `to`, into `register` when there is one
+    fn operate(
+        &mut self,
+        operator: char,
+        from: &Position,
+        to: &Position,
+        reach: Reach,
+        register: Option<char>,
+    ) {
+        let (start, end) = if from <= to {
+            (from.clone(), to.clone())
+        } else {
+            (to.clone(), from.clone())
+        };
+        if reach == Reach::Lines {
+            let lines = start.y as usize..(end.y as usize + 1).min(self.document.len());
+            if lines.is_empty() {
+                return;
+            }
+            let texts: Vec<String> = lines
+                .clone()
+                .filter_map(|y| self.document.row(y))
+                .map(|row| row.substring(0, row.len()))
+                .collect();
+            self.yank(texts.join("\n") + "\n", register);
+            match operator {
+                'd' => {
+                    self.document.delete_lines(lines);
+                    let y = (start.y as usize).min(self.last_line()) as u16;
+                    self.cursor_position = Position {
+                        x: self.first_non_blank(y),
+                        y,
+                    };
+                }
+                'c' => {
+                    let indentation = Row::from(texts[0].as_str()).indentation().to_string();
+                    self.document
+                        .replace_lines(lines, std::slice::from_ref(&indentation));
+                    self.cursor_position = Position {
+                        x: indentation.graphemes(true).count() as u16,
+                        y: start.y,
+                    };
+                    self.set_mode(Mode::Insert);
+                }
+                _ => self.cursor_position.y = start.y,
+            }
+            return;
+        }
+        let end = if reach == Reach::Inclusive {
+            Position {
+                x: (end.x + 1).min(self.row_len(end.y)),
+                y: end.y,
+            }
+        } else {
+            end
+        };
+        self.yank(self.document.text(&start, &end), register);
+        if operator != 'y' {
+            self.document.delete_range(&start, &end);
+        }
+        self.cursor_position = start;
+        if operator == 'c' {
+            self.set_mode(Mode::Insert);
+        }
+    }
+
+    fn yank(&mut self, text: String, register: Option<char>) {
+        match register {
+            Some(name) if !Registers::is_name(name.to_ascii_lowercase()) => {
+                self.status_message = StatusMessage::from(format!("{name} is not a register."));
+            }
+            register => self.store(text, false, register, "Yanked"),
+        }
+    }
+
+    /// put the clipboard, or `register`, after the cursor or before it; text ending in
+    /// a line break goes in as whole lines below, or above, the current one
+    fn vim_paste(&mut self, after: bool, count: usize, register: Option<char>) {
+        let text = match register {
+            Some(name) => {
+                let Some(text) = self.registers.get(name) else {
+                    self.status_message = StatusMessage::from(format!("Register {name} is empty."));
+                    return;
+                };
+                text.to_string()
+            }
+            None => self.clipboard.paste().0,
+        };
+        let text = text.replace("\r\n", "\n").repeat(count);
+        let Position { x, y } = self.cursor_position;
+        if let Some(lines) = text.strip_suffix('\n') {
+            let y = if after { y + 1 } else { y };
+            if y as usize >= self.document.len() && !self.document.is_empty() {
+                let last = self.last_line() as u16;
+                let len = self.row_len(last);
+                self.document
+                    .insert_str(&Position { x: len, y: last }, &format!("\n{lines}"));
+            } else {
+                self.document.insert_str(&Position { x: 0, y }, &text);
+            }
+            self.cursor_position = Position {
+                x: self.first_non_blank(y),
+                y,
+            };
+        } else {
+            let at = if after {
+                (x + 1).min(self.row_len(y))
+            } else {
+                x
+            };
+            let end = self.document.insert_str(&Position { x: at, y }, &text);
+            self.cursor_position = Position {
+                x: end.x.saturating_sub(1),
+                y: end.y,
+            };
+        }
+    }
+
+    /// read and run an ex command: `:w`, `:q`, `:q!`, `:wq` or `:x`, or a line number
+    fn vim_command_line(&mut self) -> Result<()> {
+        self.set_mode(Mode::Command);
+        let line = self.prompt(":", |_, _, _| {});
+        self.set_mode(Mode::Normal);
+        self.selection = None;
+        let Some(line) = line? else {
+            return Ok(());
+        };
+        match line.trim() {
+            "w" => self.save(),
+            "q" => self.quit(),
+            "q!" => self.should_quit = true,
+            "wq" | "x" => {
+                self.save();
+                self.should_quit = !self.document.is_dirty();
+            }
+            command => {
+                if let Ok(line) = command.parse::<usize>() {
+                    let y = line.saturating_sub(1).min(self.last_line()) as u16;
+                    self.cursor_position = Position {
+                        x: self.first_non_blank(y),
+                        y,
+                    };
+                } else {
+                    self.status_message =
+                        StatusMessage::from(format!("Not an editor command: {command}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// in normal mode the cursor sits on a character, not past the end of the row
+    fn keep_on_text(&mut self) {
+        if self.vim.as_ref().is_none_or(|vim| vim.mode == Mode::Insert) {
+            return;
+        }
+        let y = (self.cursor_position.y as usize).min(self.last_line()) as u16;
+        self.cursor_position = Position {
+            x: self
+                .cursor_position
+                .x
+                .min(self.row_len(y).saturating_sub(1)),
+            y,
+        };
+    }
+
+    fn row_len(&self, y: u16) -> u16 {
+        self.document.row(y as usize).map_or(0, Row::len) as u16
+    }
+
+    fn last_line(&self) -> usize {
+        self.document.len().saturating_sub(1)
+    }
+
+    fn first_non_blank(&self, y: u16) -> u16 {
+        self.document
+            .row(y as usize)
+            .map_or(0, |row| row.indentation().len()) as u16
+    }
+
+    /// the character at `position`, a line break past the end of a row
+    fn char_at(&self, position: &Position) -> char {
+        self.document
+            .row(position.y as usize)
+            .and_then(|row| {
+                let x = position.x as usize;
+                row.substring(x, x + 1).chars().next()
+            })
+            .unwrap_or('\n')
+    }
+
+    /// the position one character back, across line breaks
+    fn previous_position(&self, position: &Position) -> Position {
+        if position.x > 0 {
+            Position {
+                x: position.x - 1,
+                y: position.y,
+            }
+        } else if position.y > 0 {
+            Position {
+                x: self.row_len(position.y - 1),
+                y: position.y - 1,
+            }
+        } else {
+            position.clone()
+        }
+    }
+
+    /// `w`: the start of the next word
+    fn word_start_after(&self, from: &Position) -> Position {
+        let mut position = from.clone();
+        let class = char_class(self.char_at(&position));
+        loop {
+            let next = self.next_position(&position);
+            if next == position {
+                return position;
+            }
+            position = next;
+            let here = char_class(self.char_at(&position));
+            if here != 0 && here != class {
+                return position;
+            }
+            if here == 0 {
+                break;
+            }
+        }
+        while char_class(self.char_at(&position)) == 0 {
+            let next = self.next_position(&position);
+            if next == position {
+                break;
+            }
+            position = next;
+        }
+        position
+    }
+
+    /// `e`: the last character of this word, or of the next one when already there
+    fn word_end_after(&self, from: &Position) -> Position {
+        let mut position = self.next_position(from);
+        while char_class(self.char_at(&position)) == 0 {
+            let next = self.next_position(&position);
+            if next == position {
+                return position;
+            }
+            position = next;
+        }
+        self.word_end(&position)
+    }
+
+    /// the last character of the word at `from`
+    fn word_end(&self, from: &Position) -> Position {
+        let mut position = from.clone();
+        let class = char_class(self.char_at(&position));
+        loop {
+            let next = self.next_position(&position);
+            if next == position || char_class(self.char_at(&next)) != class {
+                return position;
+            }
+            position = next;
+        }
+    }
+
+    /// `b`: the first character of this word, or of the previous one when already there
+    fn word_start_before(&self, from: &Position) -> Position {
+        let mut position = self.previous_position(from);
+        while char_class(self.char_at(&position)) == 0 {
+            let previous = self.previous_position(&position);
+            if previous == position {
+                return position;
+            }
+            position = previous;
+        }
+        let class = char_class(self.char_at(&position));
+        loop {
+            let previous = self.previous_position(&position);
+            if previous == position || char_class(self.char_at(&previous)) != class {
+                return position;
+            }
+            position = previous;
+        }
+    }
+}
+
+/// vim's kinds of characters: blanks, word characters and everything else
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// the count a command starts with, if any, no more than `MAX_COUNT`, and the rest of it
+fn split_count(keys: &str) -> (Option<usize>, &str) {
+    let end = keys
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(keys.len());
+    if end == 0 || keys.starts_with('0') {
+        return (None, keys);
+    }
+    let count = keys[..end]
+        .parse()
+        .map_or(MAX_COUNT, |count: usize| count.min(MAX_COUNT));
+    (Some(count), &keys[end..])
+}
+
+/// `[count] operator [count] motion`, `[count] operator operator`, or `[count] command`
+fn parse(keys: &str) -> Parsed {
+    let (count, rest) = split_count(keys);
+    let (count, operator, name) = match rest.chars().next() {
+        None => return Parsed::Incomplete,
+        Some(operator @ ('d' | 'c' | 'y')) => {
+            let (inner, name) = split_count(&rest[1..]);
+            let count = match (count, inner) {
+                (None, None) => None,
+                (outer, inner) => Some(
+                    outer
+                        .unwrap_or(1)
+                        .saturating_mul(inner.unwrap_or(1))
+                        .min(MAX_COUNT),
+                ),
+            };
+            (count, Some(operator), name)
+        }
+        Some(_) => (count, None, rest),
+    };
+    let whole_line = operator.is_some_and(|operator| name == operator.to_string());
+    if name.is_empty() || name == "g" {
+        Parsed::Incomplete
+    } else if whole_line
+        || MOTIONS.contains(&name)
+        || (operator.is_none() && ACTIONS.contains(&name))
+    {
+        Parsed::Complete(Command {
+            count,
+            operator,
+            name: name.to_string(),
+        })
+    } else {
+        Parsed::Invalid
+    }
+}
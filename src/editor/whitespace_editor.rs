@@ -0,0 +1,55 @@
+use super::Position;
+use super::StatusMessage;
+use crate::Cleanup;
+use crate::Editor;
+use crate::Result;
+use crate::Row;
+
+impl Editor {
+    /// the whitespace cleanup the settings for the open file ask for
+    fn cleanup(&self) -> Cleanup {
+        let settings = self.settings();
+        Cleanup {
+            trailing_whitespace: settings.trim_trailing_whitespace,
+            indentation: settings.fix_indentation,
+            trailing_lines: settings.trim_trailing_lines,
+        }
+    }
+
+    /// write the document, the whitespace cleanup, when asked for, an undo step of its own
+    pub(super) fn save_document(&mut self) -> Result<()> {
+        let cleanup = if self.settings().cleanup_on_save {
+            self.cleanup()
+        } else {
+            Cleanup::default()
+        };
+        let all_lines = self.settings().cleanup_all_lines;
+        self.document.begin_step(&self.cursor_position);
+        let saved = self.document.save(cleanup, all_lines);
+        self.keep_in_document();
+        saved
+    }
+
+    /// clean up the whitespace of every line, whether edited or not
+    pub(super) fn cleanup_whitespace(&mut self) {
+        let changed = self.document.cleanup_whitespace(self.cleanup(), true);
+        let message = match changed {
+            0 => String::from("No whitespace to clean up."),
+            1 => String::from("Cleaned up 1 line."),
+            _ => format!("Cleaned up {changed} lines."),
+        };
+        self.status_message = StatusMessage::from(message);
+        self.keep_in_document();
+    }
+
+    /// move the cursor back onto text a cleanup removed from under it
+    fn keep_in_document(&mut self) {
+        let y = (self.cursor_position.y as usize).min(self.document.len());
+        let len = self.document.row(y).map_or(0, Row::len);
+        self.cursor_position = Position {
+            x: self.cursor_position.x.min(len as u16),
+            y: y as u16,
+        };
+        self.scroll();
+    }
+}
@@ -0,0 +1,46 @@
+use super::Position;
+use crate::Editor;
+use crate::Row;
+
+impl Editor {
+    /// where Ctrl-Right (`forward`) or Ctrl-Left takes the cursor from `position`: the end
+    /// of the next word or start of the previous one, else the end of the row, and at
+    /// the end of a row across the line break
+    pub(super) fn word_moved(&self, position: &Position, forward: bool) -> Position {
+        let Position { x, y } = *position;
+        let Some(row) = self.document.row(y as usize) else {
+            return position.clone();
+        };
+        let words = row.words(&self.settings().word_chars);
+        let at = x as usize;
+        if forward {
+            if let Some(&(_, end)) = words.iter().find(|&&(_, end)| end > at) {
+                return Position { x: end as u16, y };
+            }
+            if at < row.len() {
+                return Position {
+                    x: row.len() as u16,
+                    y,
+                };
+            }
+            if (y as usize) + 1 < self.document.len() {
+                return Position { x: 0, y: y + 1 };
+            }
+        } else {
+            if let Some(&(start, _)) = words.iter().rev().find(|&&(start, _)| start < at) {
+                return Position { x: start as u16, y };
+            }
+            if x > 0 {
+                return Position { x: 0, y };
+            }
+            if let Some(previous) = y.checked_sub(1) {
+                let len = self.document.row(previous as usize).map_or(0, Row::len);
+                return Position {
+                    x: len as u16,
+                    y: previous,
+                };
+            }
+        }
+        position.clone()
+    }
+}
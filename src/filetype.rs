@@ -0,0 +1,77 @@
+use std::path::Path;
+
+const BRACKETS: &[char] = &['{', '(', '['];
+const SLASHES: Option<(&str, &str)> = Some(("//", ""));
+const HASH: Option<(&str, &str)> = Some(("#", ""));
+const MARKUP: Option<(&str, &str)> = Some(("<!--", "-->"));
+
+/// language specific behavior, chosen from the file's extension
+pub struct FileType {
+    name: &'static str,
+    /// characters that open a new indentation level when they end a line
+    indent_openers: &'static [char],
+    /// opening and closing characters typed in pairs, one after the other
+    auto_pairs: &'static str,
+    /// what a comment starts and ends with, the end empty for line comments
+    comment: Option<(&'static str, &'static str)>,
+}
+
+impl Default for FileType {
+    fn default() -> Self {
+        Self {
+            name: "No filetype",
+            indent_openers: &[],
+            auto_pairs: "()[]{}\"\"''",
+            comment: None,
+        }
+    }
+}
+
+impl FileType {
+    pub fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    pub fn indent_openers(&self) -> &[char] {
+        self.indent_openers
+    }
+
+    pub fn auto_pairs(&self) -> &str {
+        self.auto_pairs
+    }
+
+    pub fn comment(&self) -> Option<(&str, &str)> {
+        self.comment
+    }
+
+    pub fn from(file_name: &str) -> Self {
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        #[rustfmt::skip]
+        let (name, indent_openers, auto_pairs, comment): (&str, &'static [char], &str, _) =
+            match extension {
+                // a single quote starts a lifetime far more often than a char literal
+                "rs" => ("Rust", BRACKETS, "()[]{}\"\"", SLASHES),
+                "c" | "h" | "cc" | "cpp" | "hpp" => ("C", BRACKETS, "()[]{}\"\"''", SLASHES),
+                "go" => ("Go", BRACKETS, "()[]{}\"\"''``", SLASHES),
+                "js" | "ts" | "jsx" | "tsx" => ("JavaScript", BRACKETS, "()[]{}\"\"''``", SLASHES),
+                "css" => ("CSS", &['{'], "()[]{}\"\"''", Some(("/*", "*/"))),
+                "json" => ("JSON", &['{', '['], "[]{}\"\"", None),
+                "py" => ("Python", &[':', '{', '(', '['], "()[]{}\"\"''", HASH),
+                "yaml" | "yml" => ("YAML", &[':'], "[]{}\"\"''", HASH),
+                "toml" => ("TOML", &['{', '['], "[]{}\"\"''", HASH),
+                "sh" | "bash" | "zsh" => ("Shell", &['{', '('], "()[]{}\"\"''``", HASH),
+                "html" | "htm" | "xml" => ("HTML", &[], "<>\"\"''", MARKUP),
+                "md" | "markdown" => ("Markdown", &[], "()[]\"\"``", MARKUP),
+                _ => return Self::default(),
+            };
+        Self {
+            name,
+            indent_openers,
+            auto_pairs,
+            comment,
+        }
+    }
+}
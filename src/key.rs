@@ -0,0 +1,263 @@
+use std::fmt;
+use std::str::FromStr;
+
+use termion::event;
+
+/// Modifier keys held down together with a navigation key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+impl Modifiers {
+    pub const SHIFT: Self = Self::new(true, false, false);
+    pub const ALT: Self = Self::new(false, true, false);
+    pub const CTRL: Self = Self::new(false, false, true);
+    pub const ALT_SHIFT: Self = Self::new(true, true, false);
+    pub const CTRL_ALT: Self = Self::new(false, true, true);
+
+    const fn new(shift: bool, alt: bool, ctrl: bool) -> Self {
+        Self { shift, alt, ctrl }
+    }
+
+    /// xterm encodes modifiers as `1 + bitmask` in the last CSI parameter
+    fn from_xterm(code: u8) -> Option<Self> {
+        let mask = code.checked_sub(1)?;
+        if mask == 0 || mask > 7 {
+            return None;
+        }
+        Some(Self::new(mask & 1 != 0, mask & 2 != 0, mask & 4 != 0))
+    }
+}
+
+/// Keys that termion reports without modifiers but terminals can send with them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Nav {
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Delete,
+}
+
+/// A key press, mirroring `termion::event::Key` plus modified navigation keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Backspace,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    BackTab,
+    Delete,
+    Insert,
+    F(u8),
+    Char(char),
+    Alt(char),
+    Ctrl(char),
+    Null,
+    Esc,
+    Mod(Modifiers, Nav),
+}
+
+impl Key {
+    /// decode an escape sequence termion doesn't understand, e.g. `ESC [ 1 ; 2 D` for Shift-Left
+    pub fn from_escape(bytes: &[u8]) -> Option<Self> {
+        let params = std::str::from_utf8(bytes.strip_prefix(b"\x1b[")?).ok()?;
+        let last = params.chars().last()?;
+        let mut numbers = params[..params.len() - last.len_utf8()].split(';');
+        let first = numbers.next()?;
+        let modifiers = Modifiers::from_xterm(numbers.next()?.parse().ok()?)?;
+        let nav = match (first, last) {
+            ("1", 'D') => Nav::Left,
+            ("1", 'C') => Nav::Right,
+            ("1", 'A') => Nav::Up,
+            ("1", 'B') => Nav::Down,
+            ("1", 'H') => Nav::Home,
+            ("1", 'F') => Nav::End,
+            ("3", '~') => Nav::Delete,
+            ("5", '~') => Nav::PageUp,
+            ("6", '~') => Nav::PageDown,
+            _ => return None,
+        };
+        Some(Key::Mod(modifiers, nav))
+    }
+}
+
+impl Nav {
+    const ALL: [Self; 9] = [
+        Self::Left,
+        Self::Right,
+        Self::Up,
+        Self::Down,
+        Self::Home,
+        Self::End,
+        Self::PageUp,
+        Self::PageDown,
+        Self::Delete,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Up => "Up",
+            Self::Down => "Down",
+            Self::Home => "Home",
+            Self::End => "End",
+            Self::PageUp => "PageUp",
+            Self::PageDown => "PageDown",
+            Self::Delete => "Delete",
+        }
+    }
+}
+
+/// names for characters that don't show up well on their own
+const CHAR_NAMES: [(char, &str); 4] = [
+    ('\n', "Enter"),
+    ('\r', "Return"),
+    ('\t', "Tab"),
+    (' ', "Space"),
+];
+
+/// keys are written the way they are pressed, e.g. `a`, `Enter`, `Ctrl-s`, `Alt-Shift-Left`
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let char_name = |c: char| {
+            CHAR_NAMES
+                .iter()
+                .find(|&&(named, _)| named == c)
+                .map_or(c.to_string(), |&(_, name)| name.to_string())
+        };
+        match *self {
+            Key::Char(c) => write!(f, "{}", char_name(c)),
+            Key::Alt(c) => write!(f, "Alt-{}", char_name(c)),
+            Key::Ctrl(c) => write!(f, "Ctrl-{}", char_name(c)),
+            Key::F(n) => write!(f, "F{n}"),
+            Key::Mod(modifiers, nav) => {
+                for (held, name) in [
+                    (modifiers.ctrl, "Ctrl-"),
+                    (modifiers.alt, "Alt-"),
+                    (modifiers.shift, "Shift-"),
+                ] {
+                    if held {
+                        write!(f, "{name}")?;
+                    }
+                }
+                write!(f, "{}", nav.name())
+            }
+            key => write!(f, "{key:?}"),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("unknown key `{text}`");
+        let mut modifiers = Modifiers::new(false, false, false);
+        let mut name = text;
+        loop {
+            if let Some(rest) = name.strip_prefix("Ctrl-").filter(|rest| !rest.is_empty()) {
+                modifiers.ctrl = true;
+                name = rest;
+            } else if let Some(rest) = name.strip_prefix("Alt-").filter(|rest| !rest.is_empty()) {
+                modifiers.alt = true;
+                name = rest;
+            } else if let Some(rest) = name.strip_prefix("Shift-").filter(|rest| !rest.is_empty()) {
+                modifiers.shift = true;
+                name = rest;
+            } else {
+                break;
+            }
+        }
+        let held = modifiers.ctrl || modifiers.alt || modifiers.shift;
+        if let Some(&nav) = Nav::ALL.iter().find(|nav| nav.name() == name) {
+            return Ok(if held {
+                Key::Mod(modifiers, nav)
+            } else {
+                nav.into()
+            });
+        }
+        let mut chars = name.chars();
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => CHAR_NAMES
+                .iter()
+                .find(|&&(_, named)| named == name)
+                .map(|&(c, _)| c),
+        };
+        let key = match (c, modifiers.ctrl, modifiers.alt, modifiers.shift) {
+            (Some(c), false, false, false) => Key::Char(c),
+            (Some(c), true, false, false) => Key::Ctrl(c),
+            (Some(c), false, true, false) => Key::Alt(c),
+            (Some(_), ..) => return Err(invalid()),
+            (None, ..) if held => return Err(invalid()),
+            (None, ..) => match name {
+                "Backspace" => Key::Backspace,
+                "BackTab" => Key::BackTab,
+                "Insert" => Key::Insert,
+                "Esc" => Key::Esc,
+                "Null" => Key::Null,
+                _ => Key::F(
+                    name.strip_prefix('F')
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(invalid)?,
+                ),
+            },
+        };
+        Ok(key)
+    }
+}
+
+impl From<Nav> for Key {
+    fn from(nav: Nav) -> Self {
+        match nav {
+            Nav::Left => Key::Left,
+            Nav::Right => Key::Right,
+            Nav::Up => Key::Up,
+            Nav::Down => Key::Down,
+            Nav::Home => Key::Home,
+            Nav::End => Key::End,
+            Nav::PageUp => Key::PageUp,
+            Nav::PageDown => Key::PageDown,
+            Nav::Delete => Key::Delete,
+        }
+    }
+}
+
+impl From<event::Key> for Key {
+    fn from(key: event::Key) -> Self {
+        match key {
+            event::Key::Backspace => Key::Backspace,
+            event::Key::Left => Key::Left,
+            event::Key::Right => Key::Right,
+            event::Key::Up => Key::Up,
+            event::Key::Down => Key::Down,
+            event::Key::Home => Key::Home,
+            event::Key::End => Key::End,
+            event::Key::PageUp => Key::PageUp,
+            event::Key::PageDown => Key::PageDown,
+            event::Key::BackTab => Key::BackTab,
+            event::Key::Delete => Key::Delete,
+            event::Key::Insert => Key::Insert,
+            event::Key::F(n) => Key::F(n),
+            event::Key::Char(c) => Key::Char(c),
+            event::Key::Alt(c) => Key::Alt(c),
+            event::Key::Ctrl(c) => Key::Ctrl(c),
+            event::Key::Esc => Key::Esc,
+            _ => Key::Null,
+        }
+    }
+}
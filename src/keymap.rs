@@ -0,0 +1,425 @@
+use std::fs;
+use std::str::FromStr;
+
+use crate::Config;
+use crate::Key;
+use crate::Modifiers;
+use crate::Nav;
+
+/// Editor commands a key sequence can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    Save,
+    Search,
+    SearchBackward,
+    Undo,
+    Redo,
+    ConvertCase,
+    Copy,
+    Cut,
+    Paste,
+    CursorAtNextMatch,
+    CursorsAtAllMatches,
+    JumpToBracket,
+    MoveLinesUp,
+    MoveLinesDown,
+    DuplicateLines,
+    DeleteLines,
+    JoinLines,
+    SortLines,
+    ToggleRecording,
+    PlayMacro,
+    ToggleComment,
+    CursorAbove,
+    CursorBelow,
+    Cancel,
+    Indent,
+    Dedent,
+    RepeatCount,
+    DocumentStart,
+    DocumentEnd,
+    KillLine,
+    SetMark,
+    ListBindings,
+    ToggleOverwrite,
+    TransposeChars,
+    TransposeWords,
+    TransposeLines,
+    IncrementNumber,
+    DecrementNumber,
+    FillParagraph,
+    CleanupWhitespace,
+    ExpandSnippet,
+    Complete,
+    CopyToRegister,
+    CutToRegister,
+    PasteFromRegister,
+    ListRegisters,
+    /// move the cursors the way the key does
+    Move(Key),
+    /// move the cursors the way the key does, selecting as they go
+    Select(Key),
+    /// move the corner of a rectangular selection the way the key does
+    Block(Key),
+    /// delete text the way the key does
+    Edit(Key),
+}
+
+/// every command, by the name keymap files use for it
+#[rustfmt::skip]
+const NAMES: &[(Command, &str)] = &[
+    (Command::Quit, "quit"),
+    (Command::Save, "save"),
+    (Command::Search, "search"),
+    (Command::SearchBackward, "search_backward"),
+    (Command::Undo, "undo"),
+    (Command::Redo, "redo"),
+    (Command::ConvertCase, "convert_case"),
+    (Command::Copy, "copy"),
+    (Command::Cut, "cut"),
+    (Command::Paste, "paste"),
+    (Command::CursorAtNextMatch, "cursor_at_next_match"),
+    (Command::CursorsAtAllMatches, "cursors_at_all_matches"),
+    (Command::JumpToBracket, "jump_to_bracket"),
+    (Command::MoveLinesUp, "move_lines_up"),
+    (Command::MoveLinesDown, "move_lines_down"),
+    (Command::DuplicateLines, "duplicate_lines"),
+    (Command::DeleteLines, "delete_lines"),
+    (Command::JoinLines, "join_lines"),
+    (Command::SortLines, "sort_lines"),
+    (Command::ToggleRecording, "toggle_recording"),
+    (Command::PlayMacro, "play_macro"),
+    (Command::ToggleComment, "toggle_comment"),
+    (Command::CursorAbove, "cursor_above"),
+    (Command::CursorBelow, "cursor_below"),
+    (Command::Cancel, "cancel"),
+    (Command::Indent, "indent"),
+    (Command::Dedent, "dedent"),
+    (Command::RepeatCount, "repeat_count"),
+    (Command::DocumentStart, "document_start"),
+    (Command::DocumentEnd, "document_end"),
+    (Command::KillLine, "kill_line"),
+    (Command::SetMark, "set_mark"),
+    (Command::ListBindings, "list_bindings"),
+    (Command::ToggleOverwrite, "toggle_overwrite"),
+    (Command::TransposeChars, "transpose_chars"),
+    (Command::TransposeWords, "transpose_words"),
+    (Command::TransposeLines, "transpose_lines"),
+    (Command::IncrementNumber, "increment_number"),
+    (Command::DecrementNumber, "decrement_number"),
+    (Command::FillParagraph, "fill_paragraph"),
+    (Command::CleanupWhitespace, "cleanup_whitespace"),
+    (Command::ExpandSnippet, "expand_snippet"),
+    (Command::Complete, "complete"),
+    (Command::CopyToRegister, "copy_to_register"),
+    (Command::CutToRegister, "cut_to_register"),
+    (Command::PasteFromRegister, "paste_from_register"),
+    (Command::ListRegisters, "list_registers"),
+    (Command::Move(Key::Left), "left"),
+    (Command::Move(Key::Right), "right"),
+    (Command::Move(Key::Up), "up"),
+    (Command::Move(Key::Down), "down"),
+    (Command::Move(Key::Home), "line_start"),
+    (Command::Move(Key::End), "line_end"),
+    (Command::Move(Key::PageUp), "page_up"),
+    (Command::Move(Key::PageDown), "page_down"),
+    (Command::Move(Key::Mod(Modifiers::CTRL, Nav::Left)), "word_left"),
+    (Command::Move(Key::Mod(Modifiers::CTRL, Nav::Right)), "word_right"),
+    (Command::Select(Key::Left), "select_left"),
+    (Command::Select(Key::Right), "select_right"),
+    (Command::Select(Key::Up), "select_up"),
+    (Command::Select(Key::Down), "select_down"),
+    (Command::Select(Key::Home), "select_line_start"),
+    (Command::Select(Key::End), "select_line_end"),
+    (Command::Select(Key::PageUp), "select_page_up"),
+    (Command::Select(Key::PageDown), "select_page_down"),
+    (Command::Block(Key::Left), "block_left"),
+    (Command::Block(Key::Right), "block_right"),
+    (Command::Block(Key::Up), "block_up"),
+    (Command::Block(Key::Down), "block_down"),
+    (Command::Block(Key::Home), "block_line_start"),
+    (Command::Block(Key::End), "block_line_end"),
+    (Command::Block(Key::PageUp), "block_page_up"),
+    (Command::Block(Key::PageDown), "block_page_down"),
+    (Command::Edit(Key::Delete), "delete"),
+    (Command::Edit(Key::Backspace), "backspace"),
+    // Ctrl-Backspace, which terminals send as Ctrl-H
+    (Command::Edit(Key::Ctrl('h')), "delete_word_backward"),
+    (Command::Edit(Key::Mod(Modifiers::CTRL, Nav::Delete)), "delete_word_forward"),
+];
+
+impl Command {
+    pub fn name(self) -> &'static str {
+        NAMES
+            .iter()
+            .find(|&&(command, _)| command == self)
+            .map_or("unknown", |&(_, name)| name)
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        NAMES
+            .iter()
+            .find(|&&(_, named)| named == name)
+            .map(|&(command, _)| command)
+            .ok_or_else(|| format!("unknown command `{name}`"))
+    }
+}
+
+type Binding = (&'static [Key], Command);
+
+/// the eight keys that move the cursor, each also bound with Shift to select and
+/// with Alt-Shift to draw a rectangle
+const NAV_KEYS: [(Nav, Key); 8] = [
+    (Nav::Left, Key::Left),
+    (Nav::Right, Key::Right),
+    (Nav::Up, Key::Up),
+    (Nav::Down, Key::Down),
+    (Nav::Home, Key::Home),
+    (Nav::End, Key::End),
+    (Nav::PageUp, Key::PageUp),
+    (Nav::PageDown, Key::PageDown),
+];
+
+#[rustfmt::skip]
+const DEFAULT: &[Binding] = &[
+    (&[Key::Ctrl('q')], Command::Quit),
+    (&[Key::Ctrl('s')], Command::Save),
+    (&[Key::Ctrl('f')], Command::Search),
+    (&[Key::Ctrl('z')], Command::Undo),
+    (&[Key::Ctrl('y')], Command::Redo),
+    (&[Key::Alt('c')], Command::ConvertCase),
+    (&[Key::Ctrl('c')], Command::Copy),
+    (&[Key::Ctrl('x')], Command::Cut),
+    (&[Key::Ctrl('v')], Command::Paste),
+    (&[Key::Ctrl('d')], Command::CursorAtNextMatch),
+    (&[Key::Alt('\n')], Command::CursorsAtAllMatches),
+    (&[Key::Alt('\r')], Command::CursorsAtAllMatches),
+    // Ctrl-], which termion reports by the digit sharing its control code
+    (&[Key::Ctrl('5')], Command::JumpToBracket),
+    (&[Key::Mod(Modifiers::ALT, Nav::Up)], Command::MoveLinesUp),
+    (&[Key::Mod(Modifiers::ALT, Nav::Down)], Command::MoveLinesDown),
+    (&[Key::Alt('d')], Command::DuplicateLines),
+    (&[Key::Ctrl('k')], Command::DeleteLines),
+    (&[Key::Alt('j')], Command::JoinLines),
+    (&[Key::Alt('s')], Command::SortLines),
+    (&[Key::Alt('r')], Command::ToggleRecording),
+    (&[Key::Alt('p')], Command::PlayMacro),
+    // Ctrl-/, which terminals send as Ctrl-_ and termion reports as Ctrl-7
+    (&[Key::Ctrl('7')], Command::ToggleComment),
+    (&[Key::Mod(Modifiers::CTRL_ALT, Nav::Up)], Command::CursorAbove),
+    (&[Key::Mod(Modifiers::CTRL_ALT, Nav::Down)], Command::CursorBelow),
+    (&[Key::Esc], Command::Cancel),
+    (&[Key::Char('\t')], Command::Indent),
+    (&[Key::BackTab], Command::Dedent),
+    (&[Key::Ctrl('u')], Command::RepeatCount),
+    (&[Key::F(1)], Command::ListBindings),
+    (&[Key::Insert], Command::ToggleOverwrite),
+    (&[Key::Ctrl('t')], Command::TransposeChars),
+    (&[Key::Alt('t')], Command::TransposeWords),
+    (&[Key::Alt('T')], Command::TransposeLines),
+    (&[Key::Alt('+')], Command::IncrementNumber),
+    (&[Key::Alt('-')], Command::DecrementNumber),
+    (&[Key::Alt('q')], Command::FillParagraph),
+    (&[Key::Alt('W')], Command::CleanupWhitespace),
+    (&[Key::Alt('e')], Command::ExpandSnippet),
+    (&[Key::Alt('/')], Command::Complete),
+    (&[Key::Alt('y')], Command::CopyToRegister),
+    (&[Key::Alt('k')], Command::CutToRegister),
+    (&[Key::Alt('v')], Command::PasteFromRegister),
+    (&[Key::F(2)], Command::ListRegisters),
+    (&[Key::Mod(Modifiers::CTRL, Nav::Left)], Command::Move(Key::Mod(Modifiers::CTRL, Nav::Left))),
+    (&[Key::Mod(Modifiers::CTRL, Nav::Right)], Command::Move(Key::Mod(Modifiers::CTRL, Nav::Right))),
+    (&[Key::Delete], Command::Edit(Key::Delete)),
+    (&[Key::Backspace], Command::Edit(Key::Backspace)),
+    (&[Key::Ctrl('h')], Command::Edit(Key::Ctrl('h'))),
+    (&[Key::Mod(Modifiers::CTRL, Nav::Delete)], Command::Edit(Key::Mod(Modifiers::CTRL, Nav::Delete))),
+];
+
+/// bindings the emacs keymap puts over the default ones
+#[rustfmt::skip]
+const EMACS: &[Binding] = &[
+    (&[Key::Ctrl('a')], Command::Move(Key::Home)),
+    (&[Key::Ctrl('e')], Command::Move(Key::End)),
+    (&[Key::Ctrl('b')], Command::Move(Key::Left)),
+    (&[Key::Ctrl('f')], Command::Move(Key::Right)),
+    (&[Key::Ctrl('p')], Command::Move(Key::Up)),
+    (&[Key::Ctrl('n')], Command::Move(Key::Down)),
+    (&[Key::Alt('b')], Command::Move(Key::Mod(Modifiers::CTRL, Nav::Left))),
+    (&[Key::Alt('f')], Command::Move(Key::Mod(Modifiers::CTRL, Nav::Right))),
+    (&[Key::Alt('v')], Command::Move(Key::PageUp)),
+    (&[Key::Ctrl('v')], Command::Move(Key::PageDown)),
+    (&[Key::Alt('<')], Command::DocumentStart),
+    (&[Key::Alt('>')], Command::DocumentEnd),
+    (&[Key::Ctrl('d')], Command::Edit(Key::Delete)),
+    (&[Key::Ctrl('k')], Command::KillLine),
+    (&[Key::Ctrl('w')], Command::Cut),
+    (&[Key::Alt('w')], Command::Copy),
+    (&[Key::Ctrl('y')], Command::Paste),
+    // Ctrl-Space, which terminals send as a NUL byte
+    (&[Key::Null], Command::SetMark),
+    (&[Key::Ctrl('g')], Command::Cancel),
+    (&[Key::Ctrl('s')], Command::Search),
+    (&[Key::Ctrl('r')], Command::SearchBackward),
+    (&[Key::Ctrl('7')], Command::Undo),
+    (&[Key::Alt('_')], Command::Redo),
+    (&[Key::Alt(';')], Command::ToggleComment),
+    (&[Key::Ctrl('x'), Key::Ctrl('s')], Command::Save),
+    (&[Key::Ctrl('x'), Key::Ctrl('c')], Command::Quit),
+    (&[Key::Ctrl('x'), Key::Char('u')], Command::Undo),
+    (&[Key::Ctrl('x'), Key::Ctrl('t')], Command::TransposeLines),
+    (&[Key::Ctrl('x'), Key::Char('r'), Key::Char('s')], Command::CopyToRegister),
+    (&[Key::Ctrl('x'), Key::Char('r'), Key::Char('i')], Command::PasteFromRegister),
+    (&[Key::Ctrl('x'), Key::Char('r'), Key::Char('l')], Command::ListRegisters),
+];
+
+/// what a sequence of keys typed so far is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    Unbound,
+    /// the start of a longer sequence, waiting for more keys
+    Prefix,
+    Command(Command),
+}
+
+/// Key sequences and the commands they run.
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, Command)>,
+}
+
+impl Keymap {
+    /// the bindings named by the `keymap` setting; vim's normal mode sits on top of
+    /// the default ones
+    pub fn new(name: &str) -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+        };
+        for (nav, key) in NAV_KEYS {
+            keymap.bind(&[key], Some(Command::Move(key)));
+            keymap.bind(
+                &[Key::Mod(Modifiers::SHIFT, nav)],
+                Some(Command::Select(key)),
+            );
+            keymap.bind(
+                &[Key::Mod(Modifiers::ALT_SHIFT, nav)],
+                Some(Command::Block(key)),
+            );
+        }
+        for &(keys, command) in DEFAULT {
+            keymap.bind(keys, Some(command));
+        }
+        if name == "emacs" {
+            for &(keys, command) in EMACS {
+                keymap.bind(keys, Some(command));
+            }
+        }
+        keymap
+    }
+
+    /// the bindings named by the `keymap` setting with the keymap file's lines put over
+    /// them, collecting a message for every line that could not be applied
+    pub fn load(name: &str) -> (Self, Vec<String>) {
+        let mut keymap = Self::new(name);
+        let mut errors = Vec::new();
+        let Some(path) = Config::directory().map(|directory| directory.join("keymap")) else {
+            return (keymap, errors);
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return (keymap, errors);
+        };
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Err(err) = keymap.bind_line(line) {
+                errors.push(format!("{}:{}: {}", path.display(), index + 1, err));
+            }
+        }
+        (keymap, errors)
+    }
+
+    /// apply `keys = command`, where the keys are pressed one after another and
+    /// `none` unbinds them
+    fn bind_line(&mut self, line: &str) -> Result<(), String> {
+        let (keys, name) = line
+            .rsplit_once('=')
+            .ok_or_else(|| "expected `keys = command`".to_string())?;
+        let keys = keys
+            .split_whitespace()
+            .map(Key::from_str)
+            .collect::<Result<Vec<Key>, String>>()?;
+        if keys.is_empty() {
+            return Err("expected keys before `=`".to_string());
+        }
+        let command = match name.trim() {
+            "none" => None,
+            name => Some(name.parse()?),
+        };
+        self.bind(&keys, command);
+        Ok(())
+    }
+
+    /// bind `keys` to `command`, or unbind them, dropping the bindings either one
+    /// would hide by being the start of the other
+    fn bind(&mut self, keys: &[Key], command: Option<Command>) {
+        self.bindings
+            .retain(|(bound, _)| !bound.starts_with(keys) && !keys.starts_with(bound));
+        if let Some(command) = command {
+            self.bindings.push((keys.to_vec(), command));
+        }
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut lookup = Lookup::Unbound;
+        for (bound, command) in &self.bindings {
+            if bound == keys {
+                return Lookup::Command(*command);
+            }
+            if bound.starts_with(keys) {
+                lookup = Lookup::Prefix;
+            }
+        }
+        lookup
+    }
+
+    /// the key sequence bound to `command` last, written out as typed
+    pub fn keys_for(&self, command: Command) -> Option<String> {
+        self.bindings
+            .iter()
+            .rev()
+            .find(|&&(_, bound)| bound == command)
+            .map(|(keys, _)| sequence_text(keys))
+    }
+
+    /// every binding as a `keys  command` line, in the order of the command table
+    pub fn describe(&self) -> Vec<String> {
+        let width = self
+            .bindings
+            .iter()
+            .map(|(keys, _)| sequence_text(keys).len())
+            .max()
+            .unwrap_or(0);
+        NAMES
+            .iter()
+            .flat_map(|&(command, name)| {
+                self.bindings
+                    .iter()
+                    .filter(move |&&(_, bound)| bound == command)
+                    .map(move |(keys, _)| format!("{:width$}  {name}", sequence_text(keys)))
+            })
+            .collect()
+    }
+}
+
+/// keys pressed one after another, e.g. `Ctrl-x Ctrl-s`
+pub fn sequence_text(keys: &[Key]) -> String {
+    keys.iter()
+        .map(Key::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -5,22 +5,51 @@
     clippy::shadow_reuse,
     clippy::print_stdout,
     clippy::wildcard_enum_match_arm,
-    clippy::else_if_without_else
+    clippy::else_if_without_else,
+    clippy::cast_possible_truncation,
+    clippy::uninlined_format_args,
+    clippy::manual_instant_elapsed,
+    clippy::unnecessary_semicolon,
+    clippy::collapsible_match,
+    clippy::implicit_saturating_sub,
+    clippy::single_match_else,
+    clippy::semicolon_if_nothing_returned,
+    clippy::empty_line_after_outer_attr
 )]
 #[macro_use]
 extern crate clap;
 mod args;
+mod clipboard;
+mod config;
 mod document;
 mod editor;
+mod filetype;
+mod key;
+mod keymap;
+mod registers;
 mod row;
+mod snippet;
 mod terminal;
 
 use args::Args;
+use clipboard::Clipboard;
+use config::Config;
+use document::Cleanup;
 use document::Document;
+use document::Indent;
 use editor::Editor;
 use editor::Position;
 use editor::SearchDirection;
+use filetype::FileType;
+use key::Key;
+use key::Modifiers;
+use key::Nav;
+use keymap::Command;
+use keymap::Keymap;
+use keymap::Lookup;
+use registers::Registers;
 use row::Row;
+use snippet::Snippet;
 use terminal::Terminal;
 
 pub type Error = std::io::Error;
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::Config;
+use crate::Result;
+
+/// Text kept under a one-character name, saved between sessions.
+///
+/// `a` to `z` hold what is copied or cut into them, the uppercase letter adding to the
+/// end instead; `/` holds the last search and `.` the last text typed.
+#[derive(Default)]
+pub struct Registers {
+    texts: BTreeMap<char, String>,
+}
+
+impl Registers {
+    /// the registers saved by the last session, none when there is no state file
+    pub fn load() -> Self {
+        let mut registers = Self::default();
+        let Some(contents) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return registers;
+        };
+        for line in contents.lines() {
+            let mut chars = line.chars();
+            if let (Some(name), Some(' ')) = (chars.next(), chars.next()) {
+                if Self::is_name(name) {
+                    registers.texts.insert(name, unescaped(chars.as_str()));
+                }
+            }
+        }
+        registers
+    }
+
+    /// write every register to the state file
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let mut contents = String::new();
+        for (name, text) in &self.texts {
+            contents.push(*name);
+            contents.push(' ');
+            contents.push_str(&escaped(text));
+            contents.push('\n');
+        }
+        fs::write(path, contents)
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(Config::directory()?.join("registers"))
+    }
+
+    /// whether `name` is a register, which `set` takes uppercase letters for too
+    pub fn is_name(name: char) -> bool {
+        name.is_ascii_lowercase() || name == '/' || name == '.'
+    }
+
+    pub fn get(&self, name: char) -> Option<&str> {
+        self.texts.get(&name).map(String::as_str)
+    }
+
+    /// put `text` in register `name`, or add it to the end for an uppercase letter;
+    /// returns whether there is such a register
+    pub fn set(&mut self, name: char, text: &str) -> bool {
+        if name.is_ascii_uppercase() {
+            self.append(name.to_ascii_lowercase(), text);
+        } else if Self::is_name(name) {
+            self.texts.insert(name, text.to_string());
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// add `text` to the end of register `name`
+    pub fn append(&mut self, name: char, text: &str) {
+        if Self::is_name(name) {
+            self.texts.entry(name).or_default().push_str(text);
+        }
+    }
+
+    /// every register holding text, as `name  text` with line breaks written `\n`
+    pub fn describe(&self) -> Vec<(char, String)> {
+        self.texts
+            .iter()
+            .map(|(&name, text)| (name, format!("{name}  {}", escaped(text))))
+            .collect()
+    }
+}
+
+/// `text` on one line: backslashes, line breaks and carriage returns escaped
+fn escaped(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescaped(line: &str) -> String {
+    let mut text = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some(c) => text.push(c),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
@@ -1,46 +1,109 @@
-use std::cmp;
-use unicode_segmentation::UnicodeSegmentation;
+use unicode_segmentation::{Graphemes, UnicodeSegmentation};
 
 use crate::SearchDirection;
 
-#[derive(Default, Debug)]
+#[derive(Debug, Clone)]
 pub struct Row {
     content: String,
     len: usize,
+    /// changed or added since the file was read
+    edited: bool,
+}
+
+impl Default for Row {
+    fn default() -> Self {
+        Row::from("")
+    }
 }
 
 impl From<&str> for Row {
     fn from(slice: &str) -> Self {
         let content = slice.to_string();
         let len = content[..].graphemes(true).count();
-        Row { content, len }
+        Row {
+            content,
+            len,
+            edited: true,
+        }
     }
 }
 
 impl Row {
-    /// render a document's row into terminal
-    pub fn render(&self, start: usize, end: usize) -> String {
-        let end = cmp::min(end, self.content.len());
-        let start = cmp::min(start, end);
-        self.content[..]
+    /// a row as read from the file, not edited yet
+    pub fn original(slice: &str) -> Self {
+        Row {
+            edited: false,
+            ..Row::from(slice)
+        }
+    }
+
+    pub fn is_edited(&self) -> bool {
+        self.edited
+    }
+
+    /// render the display columns `start..end` of a document's row into terminal,
+    /// tabs expanded to the next multiple of `tab_width` and the columns of a wide
+    /// grapheme cut by either end left blank
+    pub fn render(&self, start: usize, end: usize, tab_width: usize) -> String {
+        let mut column = 0;
+        let mut rendered = String::new();
+        for grapheme in self.content[..].graphemes(true) {
+            let width = grapheme_width(grapheme, column, tab_width);
+            let shown = (column + width).min(end).saturating_sub(column.max(start));
+            if grapheme == "\t" || (shown > 0 && shown < width) {
+                rendered.push_str(&" ".repeat(shown));
+            } else if shown > 0 {
+                rendered.push_str(grapheme);
+            }
+            column += width;
+            if column >= end {
+                break;
+            }
+        }
+        rendered
+    }
+
+    /// the number of terminal columns the row takes up
+    pub fn width(&self, tab_width: usize) -> usize {
+        self.display_column(self.len, tab_width)
+    }
+
+    /// the display column grapheme `index` starts at, counting one column
+    /// per grapheme past the end of the row
+    pub fn display_column(&self, index: usize, tab_width: usize) -> usize {
+        let width = self.content[..]
             .graphemes(true)
-            .skip(start)
-            .take(end - start)
-            .map(|grapheme| if grapheme == "\t" { " " } else { grapheme })
-            .collect::<String>()
+            .take(index)
+            .fold(0, |column, grapheme| {
+                column + grapheme_width(grapheme, column, tab_width)
+            });
+        width + index.saturating_sub(self.len)
+    }
+
+    /// the index of the first grapheme starting at or after display column `column`
+    pub fn index_at_column(&self, column: usize, tab_width: usize) -> usize {
+        let mut start = 0;
+        for (index, grapheme) in self.content[..].graphemes(true).enumerate() {
+            if start >= column {
+                return index;
+            }
+            start += grapheme_width(grapheme, start, tab_width);
+        }
+        self.len
     }
 
     pub fn len(&self) -> usize {
         self.len
     }
 
-    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         self.content.is_empty()
     }
 
+    /// recount the graphemes after the content changed, which marks the row edited
     pub fn update_len(&mut self) {
         self.len = self.content[..].graphemes(true).count();
+        self.edited = true;
     }
 
     pub fn insert(&mut self, at: usize, ch: char) {
@@ -67,12 +130,43 @@ impl Row {
         self.update_len();
     }
 
+    pub fn insert_str(&mut self, at: usize, text: &str) {
+        let mut updated_content: String = self.content[..].graphemes(true).take(at).collect();
+        let remainer: String = self.content[..].graphemes(true).skip(at).collect();
+        updated_content.push_str(text);
+        updated_content.push_str(&remainer);
+        self.content = updated_content;
+        self.update_len();
+    }
+
+    /// remove the graphemes in `start..end`
+    pub fn delete_range(&mut self, start: usize, end: usize) {
+        let mut update_content: String = self.content[..].graphemes(true).take(start).collect();
+        let remainer: String = self.content[..].graphemes(true).skip(end).collect();
+        update_content.push_str(&remainer);
+        self.content = update_content;
+        self.update_len();
+    }
+
+    /// the graphemes in `start..end` as a string
+    pub fn substring(&self, start: usize, end: usize) -> String {
+        self.content[..]
+            .graphemes(true)
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .collect()
+    }
+
     pub fn split(&mut self, at: usize) -> Self {
         let benning: String = self.content[..].graphemes(true).take(at).collect();
         let remainer: String = self.content[..].graphemes(true).skip(at).collect();
         self.content = benning;
         self.update_len();
-        return Row::from(remainer.as_str());
+        Row::from(remainer.as_str())
+    }
+
+    pub fn graphemes(&self) -> Graphemes<'_> {
+        self.content[..].graphemes(true)
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -101,7 +195,7 @@ impl Row {
         };
         if let Some(matching_byte_index) = matching_byte_index {
             for (grapheme_index, (byte_index, _)) in
-                self.content[..].grapheme_indices(true).enumerate()
+                substring[..].grapheme_indices(true).enumerate()
             {
                 if matching_byte_index == byte_index {
                     return Some(start + grapheme_index);
@@ -111,8 +205,77 @@ impl Row {
         None
     }
 
+    /// the bounds of the word at or just before column `at`, as `words` splits them,
+    /// empty when there is none
+    pub fn word_at(&self, at: usize, word_chars: &str) -> (usize, usize) {
+        let words = self.words(word_chars);
+        words
+            .iter()
+            .find(|&&(start, end)| start <= at && at < end)
+            .or_else(|| words.iter().find(|&&(_, end)| end == at))
+            .copied()
+            .unwrap_or((at, at))
+    }
+
+    /// the graphemes `start..end` of every word, split at Unicode word boundaries except
+    /// around runs of `word_chars`, which join the words on either side
+    pub fn words(&self, word_chars: &str) -> Vec<(usize, usize)> {
+        let mut words: Vec<(usize, usize)> = Vec::new();
+        let (mut start, mut joining) = (0, false);
+        for segment in self.content[..].split_word_bounds() {
+            let end = start + segment.graphemes(true).count();
+            let joins = segment.chars().all(|c| word_chars.contains(c));
+            if joins || segment.chars().any(char::is_alphanumeric) {
+                match words.last_mut() {
+                    Some(last) if last.1 == start && (joins || joining) => last.1 = end,
+                    _ => words.push((start, end)),
+                }
+            }
+            joining = joins;
+            start = end;
+        }
+        words
+    }
+
+    /// the whitespace the row starts with
+    pub fn indentation(&self) -> &str {
+        let content = self.content.as_str();
+        &content[..content.len() - content.trim_start_matches([' ', '\t']).len()]
+    }
+
     pub fn extend(&mut self, other: &Self) {
         self.content.push_str(other.content.as_str());
         self.update_len();
     }
 }
+
+/// how many terminal columns a grapheme starting at `column` takes: wide East Asian
+/// characters and emoji take two, a tab reaches the next multiple of `tab_width`
+fn grapheme_width(grapheme: &str, column: usize, tab_width: usize) -> usize {
+    let Some(c) = grapheme.chars().next() else {
+        return 0;
+    };
+    if c == '\t' {
+        return tab_width.max(1) - column % tab_width.max(1);
+    }
+    let wide = matches!(c as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD);
+    if wide {
+        2
+    } else {
+        1
+    }
+}
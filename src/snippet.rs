@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Text expanded in place of a name, with placeholders Tab visits in turn.
+///
+/// Written on one line: `\n` and `\t` stand for a line break and an indentation level,
+/// `$1` or `${1}` is a tab stop, `${1:text}` one holding `text` to start with, and `$0`
+/// is where the cursor ends up. A stop used more than once is mirrored, and a
+/// backslash takes the next character as it is, e.g. `\$`.
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub lines: Vec<String>,
+    pub fields: Vec<Field>,
+}
+
+/// where a tab stop sits in a snippet's lines, as grapheme columns `start..end`
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub stop: usize,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// a run of text or a tab stop, with the text it holds if it says
+enum Piece {
+    Text(String),
+    Stop(usize, Option<String>),
+}
+
+impl FromStr for Snippet {
+    type Err = String;
+
+    fn from_str(body: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = body.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => text.push(unescaped(chars.next())),
+                '$' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    let mut number = String::new();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        number.push(digit);
+                    }
+                    pieces.push(Piece::Stop(stop_number(&number)?, None));
+                }
+                '$' if chars.next_if_eq(&'{').is_some() => {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    let mut number = String::new();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        number.push(digit);
+                    }
+                    if number.is_empty() {
+                        return Err("expected a tab stop number after `${`".to_string());
+                    }
+                    let mut held = None;
+                    if chars.next_if_eq(&':').is_some() {
+                        let mut default = String::new();
+                        while let Some(c) = chars.next_if(|&c| c != '}') {
+                            match c {
+                                '\\' => default.push(unescaped(chars.next())),
+                                c => default.push(c),
+                            }
+                        }
+                        held = Some(default);
+                    }
+                    if chars.next_if_eq(&'}').is_none() {
+                        return Err(format!("expected `}}` to close `${{{number}`"));
+                    }
+                    pieces.push(Piece::Stop(stop_number(&number)?, held));
+                }
+                c => text.push(c),
+            }
+        }
+        pieces.push(Piece::Text(text));
+        Ok(Self::from_pieces(&pieces))
+    }
+}
+
+impl Snippet {
+    /// lay out `pieces`, every copy of a stop holding the text its first copy that has
+    /// any holds
+    fn from_pieces(pieces: &[Piece]) -> Self {
+        let held = |stop: usize| {
+            pieces.iter().find_map(|piece| match piece {
+                Piece::Stop(number, Some(text)) if *number == stop => Some(text.as_str()),
+                _ => None,
+            })
+        };
+        let mut snippet = Self {
+            lines: vec![String::new()],
+            fields: Vec::new(),
+        };
+        for piece in pieces {
+            let text = match piece {
+                Piece::Text(text) => text.as_str(),
+                Piece::Stop(stop, _) => held(*stop).unwrap_or_default(),
+            };
+            let start = snippet
+                .lines
+                .last()
+                .map_or(0, |line| line.graphemes(true).count());
+            let line = snippet.lines.len() - 1;
+            for (index, part) in text.split('\n').enumerate() {
+                if index > 0 {
+                    snippet.lines.push(String::new());
+                }
+                if let Some(last) = snippet.lines.last_mut() {
+                    last.push_str(part);
+                }
+            }
+            if let Piece::Stop(stop, _) = piece {
+                // text held over several lines is only selected on the first one
+                let end = if snippet.lines.len() - 1 == line {
+                    start + text.graphemes(true).count()
+                } else {
+                    snippet.lines[line].graphemes(true).count()
+                };
+                snippet.fields.push(Field {
+                    stop: *stop,
+                    line,
+                    start,
+                    end,
+                });
+            }
+        }
+        snippet
+    }
+
+    /// the snippet with `base` starting every line after the first, and each tab
+    /// starting one of those lines swapped for `unit`
+    pub fn indented(mut self, base: &str, unit: &str) -> Self {
+        for (index, line) in self.lines.iter_mut().enumerate().skip(1) {
+            let rest = line.trim_start_matches('\t');
+            let tabs = line.len() - rest.len();
+            let indented = format!("{base}{}{rest}", unit.repeat(tabs));
+            let added = indented.graphemes(true).count() - line.graphemes(true).count();
+            *line = indented;
+            for field in self.fields.iter_mut().filter(|field| field.line == index) {
+                field.start += added;
+                field.end += added;
+            }
+        }
+        self
+    }
+}
+
+/// the character a backslash stands for before `c`
+fn unescaped(c: Option<char>) -> char {
+    match c {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some(c) => c,
+        None => '\\',
+    }
+}
+
+fn stop_number(number: &str) -> Result<usize, String> {
+    number
+        .parse()
+        .map_err(|_| format!("tab stop `{number}` is too large"))
+}
@@ -1,11 +1,13 @@
+use crate::Key;
 use crate::Position;
 use crate::Result;
 use std::io;
 use std::io::{stdout, Write};
 use termion::color;
-use termion::event::Key;
+use termion::event::Event;
 use termion::input::TermRead;
 use termion::raw::{IntoRawMode, RawTerminal};
+use termion::style;
 pub struct Size {
     pub width: u16,
     pub height: u16,
@@ -50,8 +52,15 @@ impl Terminal {
     /// read key from keborad
     pub fn read_key() -> Result<Key> {
         loop {
-            if let Some(key) = io::stdin().lock().keys().next() {
-                return key;
+            match io::stdin().lock().events().next() {
+                Some(Ok(Event::Key(key))) => return Ok(Key::from(key)),
+                Some(Ok(Event::Unsupported(bytes))) => {
+                    if let Some(key) = Key::from_escape(&bytes) {
+                        return Ok(key);
+                    }
+                }
+                Some(Err(err)) => return Err(err),
+                _ => (),
             }
         }
     }
@@ -81,4 +90,44 @@ impl Terminal {
     pub fn reset_fg_color() {
         print!("{}", color::Fg(color::Reset));
     }
+
+    pub fn set_invert() {
+        print!("{}", style::Invert);
+    }
+    pub fn set_underline() {
+        print!("{}", style::Underline);
+    }
+    pub fn reset_style() {
+        print!("{}", style::Reset);
+    }
+
+    /// ask the terminal to put text on the system clipboard (OSC 52),
+    /// wrapped in a passthrough sequence when running inside tmux
+    pub fn set_clipboard(text: &str) -> Result<()> {
+        let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+        if std::env::var_os("TMUX").is_some() {
+            print!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+        } else {
+            print!("{sequence}");
+        }
+        Self::flush_stdout()
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |acc, (i, &byte)| {
+            acc | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
 }